@@ -5,16 +5,23 @@ use axum::{
     response::IntoResponse,
     routing::{get, post},
 };
-use seiti_core::{BoardState, Logger, StoneMove, compute_stone_moves};
+use seiti_core::{
+    BoardState, Diagnostic, GenerateOptions, LevelOptions, LevelReport, Logger, PatternLibrary,
+    RegionOutcome, Seed, SeedQuery, SolveInfo, StoneMove, compute_stone_moves,
+};
 use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Instant};
 use tower_http::cors::{Any, CorsLayer};
 
 const DEFAULT_PORT: u16 = 3000;
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+const MAX_SEARCH_RANGE: u32 = 1_000_000;
 
 #[derive(Clone)]
 struct AppState {
     logger: Arc<dyn Logger + Send + Sync>,
+    /// 起動時に読み込んだ整地パターンのライブラリ（リクエストで省略した場合に使う）
+    patterns: Option<PatternLibrary>,
 }
 
 struct StdoutLogger;
@@ -26,7 +33,9 @@ impl Logger for StdoutLogger {
 
 #[derive(Deserialize)]
 struct GenerateReq {
-    seed: u32,
+    seed: Seed,
+    #[serde(flatten)]
+    options: GenerateOptions,
 }
 
 #[derive(Serialize)]
@@ -38,50 +47,346 @@ async fn health() -> &'static str {
     "ok"
 }
 
-async fn generate_board(Json(req): Json<GenerateReq>) -> Json<BoardState> {
-    Json(seiti_core::generate_board_state(req.seed))
+#[derive(Serialize)]
+struct GenerateResp {
+    #[serde(flatten)]
+    board: BoardState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verified_levelable: Option<bool>,
+}
+
+/// サーバーのライブラリは、同じ単位で数えるリクエストにだけ使う
+fn use_server_patterns(state: &AppState, options: &mut LevelOptions) {
+    if options.patterns.is_none() {
+        options.patterns = state.patterns.clone().filter(|p| p.unit == options.unit);
+    }
+}
+
+async fn generate_board(
+    State(state): State<AppState>,
+    Json(mut req): Json<GenerateReq>,
+) -> impl IntoResponse {
+    if !req.options.ensure_levelable {
+        let board = seiti_core::generate_board_state_from_seed(&req.seed, &req.options);
+        return (
+            axum::http::StatusCode::OK,
+            Json(GenerateResp {
+                board,
+                verified_levelable: None,
+            }),
+        )
+            .into_response();
+    }
+
+    use_server_patterns(&state, &mut req.options.level);
+    if let Err(e) = req.options.level.validate() {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(ErrorResp { error: e }),
+        )
+            .into_response();
+    }
+    // 整地を試すので、ブロッキング用スレッドで実行する
+    let result = tokio::task::spawn_blocking(move || {
+        seiti_core::generate_levelable_board_state(&req.seed, &req.options)
+    })
+    .await;
+    match result {
+        Ok((board, verified)) => (
+            axum::http::StatusCode::OK,
+            Json(GenerateResp {
+                board,
+                verified_levelable: Some(verified),
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResp {
+                error: format!("board generation failed: {e}"),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchReq {
+    start: u32,
+    end: u32,
+    #[serde(default = "default_search_limit")]
+    limit: usize,
+    #[serde(default)]
+    query: SeedQuery,
+    #[serde(default)]
+    options: GenerateOptions,
+}
+
+fn default_search_limit() -> usize {
+    DEFAULT_SEARCH_LIMIT
+}
+
+#[derive(Serialize)]
+struct SearchResp {
+    seeds: Vec<u32>,
+}
+
+async fn search_seeds(
+    State(state): State<AppState>,
+    Json(mut req): Json<SearchReq>,
+) -> impl IntoResponse {
+    if req.start > req.end || req.end - req.start >= MAX_SEARCH_RANGE {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(ErrorResp {
+                error: format!("seed range must be non-empty and at most {MAX_SEARCH_RANGE}"),
+            }),
+        )
+            .into_response();
+    }
+    use_server_patterns(&state, &mut req.options.level);
+    if let Err(e) = req.options.level.validate() {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(ErrorResp { error: e }),
+        )
+            .into_response();
+    }
+    // 探索は重いのでブロッキング用スレッドで実行する
+    let result = tokio::task::spawn_blocking(move || {
+        seiti_core::search_seeds(req.start, req.end, &req.query, &req.options, req.limit)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+    match result {
+        Ok(seeds) => (axum::http::StatusCode::OK, Json(SearchResp { seeds })).into_response(),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResp {
+                error: format!("seed search failed: {e}"),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+// 整地結果の列挙数の上限
+const MAX_ALTERNATIVES: usize = 16;
+// 制限時間のうち、整地できなかった理由の判定に残しておく割合
+const DIAGNOSE_TIME_SHARE: f64 = 0.25;
+
+fn default_alternatives() -> usize {
+    1
 }
 
 #[derive(Deserialize)]
 struct LevelReq {
     board: BoardState,
+    #[serde(flatten)]
+    options: LevelOptions,
+    /// 返す整地結果の数（目的関数の小さい順）。順次整地では最大 3k - 1 回解く
+    #[serde(default = "default_alternatives")]
+    k: usize,
 }
 
 #[derive(Serialize)]
-struct LevelResp {
+struct LevelErrorResp {
+    error: String,
+    /// 整地できない理由（フロントエンドで座標を強調表示する）
+    diagnostics: Vec<Diagnostic>,
+    /// 理由の判定に失敗した場合のエラー
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnose_error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct LevelAlternative {
     board: BoardState,
     moves: Vec<StoneMove>,
+    #[serde(flatten)]
+    solve: SolveInfo,
+    /// 領域ごとの結果（領域に分けて解いた場合のみ）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    regions: Vec<RegionOutcome>,
+    /// 色ごとの候補の数と、選んだパターン・セルごとのパターンの番号（フロントエンドで塊を描く）
+    report: LevelReport,
+}
+
+#[derive(Serialize)]
+struct LevelResp {
+    #[serde(flatten)]
+    best: LevelAlternative,
+    /// k >= 2 のときの整地結果（best を含む）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alternatives: Vec<LevelAlternative>,
+}
+
+enum LevelFailure {
+    /// 整地できない（理由つき。理由を判定できなかった場合はそのエラー）
+    Infeasible {
+        error: String,
+        diagnostics: Vec<Diagnostic>,
+        diagnose_error: Option<String>,
+    },
+    /// 制限時間までに整地結果が見つからなかった
+    Stopped(String),
+    /// 盤面・制約・パターンが不正
+    Invalid(String),
+    Internal(String),
+}
+
+/// 整地し、失敗した場合は理由を調べる（ブロッキング）
+///
+/// 制限時間を指定した場合、整地にはその `1 - DIAGNOSE_TIME_SHARE` だけを使い、
+/// 理由の判定には残りの時間を使うので、合計は制限時間に収まります。
+fn level_and_diagnose(
+    req: LevelReq,
+    logger: &(dyn Logger + Send + Sync),
+) -> Result<Vec<LevelAlternative>, LevelFailure> {
+    let started = Instant::now();
+    let time_limit = req.options.solver.time_limit;
+    let mut level_options = req.options.clone();
+    level_options.solver.time_limit = time_limit.map(|t| t * (1.0 - DIAGNOSE_TIME_SHARE));
+
+    let before = req.board.clone();
+    let outcomes = match seiti_core::level_board_alternatives(
+        req.board,
+        &level_options,
+        req.k,
+        Some(logger),
+    ) {
+        Ok(outcomes) => outcomes,
+        Err(error) if seiti_core::is_stopped(&error) => return Err(LevelFailure::Stopped(error)),
+        Err(error) if !seiti_core::is_infeasible(&error) => {
+            return Err(LevelFailure::Invalid(error));
+        }
+        Err(error) => {
+            let mut diagnose_options = req.options;
+            diagnose_options.solver.time_limit =
+                time_limit.map(|t| (t - started.elapsed().as_secs_f64()).max(0.0));
+            let (diagnostics, diagnose_error) =
+                match seiti_core::diagnose_leveling(before, &diagnose_options) {
+                    Ok(diagnostics) => (diagnostics, None),
+                    Err(e) => {
+                        logger.log(&format!("[diagnose] failed: {e}"));
+                        (Vec::new(), Some(e))
+                    }
+                };
+            return Err(LevelFailure::Infeasible {
+                error,
+                diagnostics,
+                diagnose_error,
+            });
+        }
+    };
+
+    outcomes
+        .into_iter()
+        .map(|outcome| {
+            let moves = compute_stone_moves(&before, &outcome.board)
+                .map_err(|e| LevelFailure::Internal(format!("failed to compute moves: {e}")))?;
+            Ok(LevelAlternative {
+                board: outcome.board,
+                moves,
+                solve: outcome.solve,
+                regions: outcome.regions,
+                report: outcome.report,
+            })
+        })
+        .collect()
 }
 
 async fn level_board(
     State(state): State<AppState>,
-    Json(req): Json<LevelReq>,
+    Json(mut req): Json<LevelReq>,
 ) -> impl IntoResponse {
-    let before = req.board.clone();
-    match seiti_core::level_board(req.board, Some(state.logger.as_ref())) {
-        Ok(after) => match compute_stone_moves(&before, &after) {
-            Ok(moves) => (
-                axum::http::StatusCode::OK,
-                Json(LevelResp {
-                    board: after,
-                    moves,
+    use_server_patterns(&state, &mut req.options);
+    if let Err(e) = req.options.validate() {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(ErrorResp { error: e }),
+        )
+            .into_response();
+    }
+    if req.k == 0 || req.k > MAX_ALTERNATIVES {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(ErrorResp {
+                error: format!("k must be between 1 and {MAX_ALTERNATIVES}"),
+            }),
+        )
+            .into_response();
+    }
+
+    // 整地は重いのでブロッキング用スレッドで実行する
+    let k = req.k;
+    let logger = state.logger.clone();
+    let result =
+        tokio::task::spawn_blocking(move || level_and_diagnose(req, logger.as_ref())).await;
+    let mut alternatives = match result {
+        Ok(Ok(alternatives)) => alternatives,
+        Ok(Err(LevelFailure::Infeasible {
+            error,
+            diagnostics,
+            diagnose_error,
+        })) => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                Json(LevelErrorResp {
+                    error,
+                    diagnostics,
+                    diagnose_error,
                 }),
             )
-                .into_response(),
-            Err(e) => (
+                .into_response();
+        }
+        Ok(Err(LevelFailure::Stopped(error))) => {
+            return (
+                axum::http::StatusCode::GATEWAY_TIMEOUT,
+                Json(ErrorResp { error }),
+            )
+                .into_response();
+        }
+        Ok(Err(LevelFailure::Invalid(error))) => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                Json(ErrorResp { error }),
+            )
+                .into_response();
+        }
+        Ok(Err(LevelFailure::Internal(error))) => {
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResp { error }),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            return (
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResp {
-                    error: format!("failed to compute moves: {}", e),
+                    error: format!("leveling failed: {e}"),
                 }),
             )
-                .into_response(),
-        },
-        Err(e) => (
-            axum::http::StatusCode::BAD_REQUEST,
-            Json(ErrorResp { error: e }),
-        )
-            .into_response(),
-    }
+                .into_response();
+        }
+    };
+
+    let best = alternatives.remove(0);
+    let alternatives = if k > 1 {
+        let mut all = Vec::with_capacity(alternatives.len() + 1);
+        all.push(best.clone());
+        all.extend(alternatives);
+        all
+    } else {
+        Vec::new()
+    };
+    (
+        axum::http::StatusCode::OK,
+        Json(LevelResp { best, alternatives }),
+    )
+        .into_response()
 }
 
 #[tokio::main]
@@ -91,14 +396,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .allow_methods([Method::GET, Method::POST])
         .allow_headers([header::CONTENT_TYPE]);
 
+    // SEITI_PATTERNS に整地パターンのライブラリ（TOML / JSON）を指定できる
+    let patterns = match std::env::var_os("SEITI_PATTERNS") {
+        Some(path) => {
+            let library = PatternLibrary::load(&path).map_err(|e| {
+                eprintln!("Failed to load pattern library: {e}");
+                e
+            })?;
+            println!("pattern library: {}", path.to_string_lossy());
+            Some(library)
+        }
+        None => None,
+    };
     let app_state = AppState {
         logger: Arc::new(StdoutLogger),
+        patterns,
     };
 
     let app = Router::new()
         .route("/health", get(health))
         .route("/api/board/generate", post(generate_board))
         .route("/api/board/level", post(level_board))
+        .route("/api/board/search", post(search_seeds))
         .layer(cors)
         .with_state(app_state);
 
@@ -0,0 +1,60 @@
+//! 整地パターンの候補列挙のベンチマーク
+//!
+//! シードの盤面ごとに両色の候補を列挙し、1盤面あたりの所要時間を測ります。
+//! 外周と内部を累積和で数える現在の方法（`prefix_sums`）と、セルを1つずつ調べる以前の方法（`scan`）を比べます。
+//!
+//! ```bash
+//! cargo bench -p seiti_core --bench candidates
+//! # シードの範囲と繰り返し回数を指定する（既定は 1..=30 と 20 回）
+//! SEITI_BENCH_SEEDS=1..=100 SEITI_BENCH_ROUNDS=5 cargo bench -p seiti_core --bench candidates
+//! ```
+
+mod common;
+
+use seiti_core::{BoardState, count_candidates, count_candidates_by_scan, generate_board_state};
+use std::time::Instant;
+
+fn rounds() -> u32 {
+    std::env::var("SEITI_BENCH_ROUNDS")
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(20)
+        .max(1)
+}
+
+// 盤面ごとに両色の候補を `rounds` 回列挙し、所要時間と候補の数を表示する。候補の数を返す
+fn run(
+    name: &str,
+    boards: &[BoardState],
+    rounds: u32,
+    count: fn(BoardState, u8) -> Result<usize, String>,
+) -> usize {
+    let t = Instant::now();
+    let mut candidates = 0usize;
+    for _ in 0..rounds {
+        for board in boards {
+            for color in [1u8, 2u8] {
+                candidates += count(board.clone(), color).unwrap_or(0);
+            }
+        }
+    }
+    let elapsed = t.elapsed();
+    let boards_run = boards.len().max(1) as u32 * rounds;
+    println!(
+        "{name:>11}: total={elapsed:?} per_board={:?} candidates_per_board={}",
+        elapsed / boards_run,
+        candidates / boards_run as usize,
+    );
+    candidates
+}
+
+fn main() {
+    let (start, end) = common::seed_range((1, 30));
+    let rounds = rounds();
+    let boards: Vec<_> = (start..=end).map(generate_board_state).collect();
+
+    println!("seeds {start}..={end} x {rounds}");
+    let fast = run("prefix_sums", &boards, rounds, count_candidates);
+    let scan = run("scan", &boards, rounds, count_candidates_by_scan);
+    assert_eq!(fast, scan, "the two methods must find the same candidates");
+}
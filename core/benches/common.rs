@@ -0,0 +1,12 @@
+//! ベンチマークで共通に使う設定の読み込み
+
+/// `SEITI_BENCH_SEEDS`（例: `1..=100`）からシードの範囲を読む。未指定や読めないときは `default`
+pub fn seed_range(default: (u32, u32)) -> (u32, u32) {
+    std::env::var("SEITI_BENCH_SEEDS")
+        .ok()
+        .and_then(|spec| {
+            let (a, b) = spec.split_once("..=")?;
+            Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+        })
+        .unwrap_or(default)
+}
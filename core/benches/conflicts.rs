@@ -0,0 +1,73 @@
+//! 衝突制約の表し方の比較ベンチマーク
+//!
+//! シードの盤面ごとに両色の選択問題を、衝突制約を盤面の辺ごとのクリークにした場合と、
+//! 衝突する組ごとにした場合で解き、制約の数・非ゼロ要素の数・所要時間・目的関数値を比べます。
+//! 組ごとの制約は非常に多く、exact_cover や microlp では盤面によって数分かかるので、既定では対象にしません。
+//!
+//! ```bash
+//! cargo bench -p seiti_core --bench conflicts
+//! # シードの範囲とソルバーを指定する（既定は 1..=10 と builtin,highs）
+//! SEITI_BENCH_SEEDS=1..=30 SEITI_BENCH_BACKENDS=builtin,exact_cover cargo bench -p seiti_core --bench conflicts
+//! ```
+
+mod common;
+
+use seiti_core::{SolverBackend, generate_board_state, solve_conflict_model};
+use std::time::Duration;
+
+fn backends() -> Vec<SolverBackend> {
+    let spec =
+        std::env::var("SEITI_BENCH_BACKENDS").unwrap_or_else(|_| "builtin,highs".to_string());
+    spec.split(',')
+        .filter_map(|name| serde_json::from_value(serde_json::json!(name.trim())).ok())
+        .filter(|b: &SolverBackend| b.is_available())
+        .collect()
+}
+
+#[derive(Default)]
+struct Totals {
+    constraints: usize,
+    nonzeros: usize,
+    time: Duration,
+    failed: usize,
+}
+
+fn main() {
+    let (start, end) = common::seed_range((1, 10));
+    let boards: Vec<_> = (start..=end).map(generate_board_state).collect();
+    println!("seeds {start}..={end} ({} models)", boards.len() * 2);
+
+    for backend in backends() {
+        let mut cliques = Totals::default();
+        let mut pairs = Totals::default();
+        let mut differ = 0usize;
+        for board in &boards {
+            for color in [1u8, 2u8] {
+                let a = solve_conflict_model(board.clone(), color, backend, false);
+                let b = solve_conflict_model(board.clone(), color, backend, true);
+                for (totals, result) in [(&mut cliques, &a), (&mut pairs, &b)] {
+                    match result {
+                        Ok(stats) => {
+                            totals.constraints += stats.constraints;
+                            totals.nonzeros += stats.nonzeros;
+                            totals.time += stats.solve_time;
+                        }
+                        Err(_) => totals.failed += 1,
+                    }
+                }
+                if let (Ok(a), Ok(b)) = (&a, &b)
+                    && (a.objective - b.objective).abs() > 1e-6
+                {
+                    differ += 1;
+                }
+            }
+        }
+        for (name, totals) in [("cliques", &cliques), ("pairs", &pairs)] {
+            println!(
+                "{backend:?} {name}: constraints={} nonzeros={} solve={:?} failed={}",
+                totals.constraints, totals.nonzeros, totals.time, totals.failed,
+            );
+        }
+        println!("{backend:?} objectives_differ={differ}");
+    }
+}
@@ -0,0 +1,85 @@
+//! パターン選択ソルバーの比較ベンチマーク
+//!
+//! シードの盤面を各ソルバーで整地し、所要時間と目的関数値を比べます。
+//! ソルバーごとに、同じコストの解を決め直す場合（`deterministic`、既定）と決め直さない場合を測ります。
+//! 使えるソルバーは有効な feature によります（microlp は盤面によって非常に遅くなります）。
+//!
+//! ```bash
+//! cargo bench -p seiti_core --bench solvers
+//! # シードの範囲を指定する（既定は 1..=30）
+//! SEITI_BENCH_SEEDS=1..=100 cargo bench -p seiti_core --bench solvers
+//! ```
+
+mod common;
+
+use seiti_core::{
+    LevelOptions, SolverBackend, SolverOptions, generate_board_state, level_board_with_options,
+};
+use std::time::{Duration, Instant};
+
+const BACKENDS: [SolverBackend; 4] = [
+    SolverBackend::Highs,
+    SolverBackend::ExactCover,
+    SolverBackend::Builtin,
+    SolverBackend::Microlp,
+];
+
+fn main() {
+    let (start, end) = common::seed_range((1, 30));
+    let boards: Vec<_> = (start..=end)
+        .map(|s| (s, generate_board_state(s)))
+        .collect();
+    let backends: Vec<SolverBackend> = BACKENDS.into_iter().filter(|b| b.is_available()).collect();
+
+    // シードごとの目的関数値（最初のソルバーを基準に比べる）
+    let mut reference: Vec<Option<f64>> = Vec::new();
+    println!("seeds {start}..={end}");
+    let runs: Vec<(SolverBackend, bool)> = backends
+        .iter()
+        .flat_map(|&b| [(b, true), (b, false)])
+        .collect();
+    for (k, &(backend, deterministic)) in runs.iter().enumerate() {
+        let options = LevelOptions {
+            deterministic,
+            solver: SolverOptions {
+                backend,
+                ..SolverOptions::default()
+            },
+            ..LevelOptions::default()
+        };
+
+        let mut total = Duration::ZERO;
+        let mut slowest = (Duration::ZERO, 0u32);
+        let mut failed = 0usize;
+        let mut worse = 0usize;
+        for (n, (seed, board)) in boards.iter().enumerate() {
+            let t = Instant::now();
+            let result = level_board_with_options(board.clone(), &options, None);
+            let elapsed = t.elapsed();
+            total += elapsed;
+            if elapsed > slowest.0 {
+                slowest = (elapsed, *seed);
+            }
+
+            let objective = result.ok().map(|o| o.solve.objective);
+            if objective.is_none() {
+                failed += 1;
+            }
+            if k == 0 {
+                reference.push(objective);
+            } else if let (Some(a), Some(b)) = (reference[n], objective)
+                && b > a + 1e-6
+            {
+                worse += 1;
+            }
+        }
+
+        println!(
+            "{backend:?} deterministic={deterministic}: total={total:?} mean={:?} max={:?} (seed {}) failed={failed} worse_than_{:?}={worse}",
+            total / boards.len().max(1) as u32,
+            slowest.0,
+            slowest.1,
+            backends[0],
+        );
+    }
+}
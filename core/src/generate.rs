@@ -1,6 +1,10 @@
+use crate::level::{LevelOptions, level_board_with_options};
 use crate::noise::fbm2;
-use crate::rng::rand_chance_1_in;
-use crate::types::{BOARD_SIZE, BoardState, NEIGH4, idx2};
+use crate::rng::{BoardRng, SeedableBoardRng, SplitMix64, XorShift32, seed_from_str};
+use crate::types::{
+    BOARD_SIZE, BoardState, NEIGH4, Seed, collect_territory_regions, idx2, parse_decimal_seed,
+};
+use serde::{Deserialize, Serialize};
 
 // 盤面生成パラメータ
 const SEED_XOR_MASK: u32 = 0x9e37_79b9;
@@ -12,6 +16,31 @@ const FBM_GAIN: f32 = 0.5;
 const TERRITORY_CARVE_ITERATIONS: usize = 3;
 const TERRITORY_CARVE_CHANCE_DENOM: u32 = 5; // 1/5の確率
 const MIN_COMPONENT_SIZE_FOR_SINGLE_TERRITORY: usize = 4;
+const DEAD_STONE_MIN_COMPONENT_SIZE: usize = 10;
+const DEAD_STONE_CELLS_PER_STONE: usize = 10; // 地10目につき死石は最大1つ
+const LEVELABLE_SEED_ATTEMPTS: usize = 4;
+const LEVELABLE_REPAIR_ATTEMPTS: usize = 8;
+/// 地を埋めて直した盤面のシードに付ける印
+const REPAIR_SEED_MARK: &str = "+fill";
+
+/// 盤面生成オプション
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerateOptions {
+    /// 黒白の境界に残す中立点（ダメ）の数（上限。置ける場所が足りなければ少なくなる）
+    pub neutral_points: usize,
+    /// 大きな地の中に置く相手の死石の数（上限）
+    ///
+    /// 10目以上の地の連結成分ごとに、1から「地10目につき1つ」までの数を乱数で選んで置くので、
+    /// 大きな地が少ない盤面ではこれより少なくなります（0 になることもあります）。
+    pub dead_stones: usize,
+    /// 各色のアゲハマの最大数（0..=max_prisoners から一様に選ぶ）
+    pub max_prisoners: u32,
+    /// 整地できることを確認した盤面だけを返す（[`generate_levelable_board_state`]）
+    pub ensure_levelable: bool,
+    /// `ensure_levelable` で整地を試すときの整地オプション（単位・パターンのライブラリなど）
+    pub level: LevelOptions,
+}
 
 /// 盤面状態を生成する
 ///
@@ -47,8 +76,8 @@ fn is_color_stone_or_territory_or_boundary(
     is_color_stone_or_territory(stones, territory, x as usize, y as usize, color)
 }
 
-fn carve_territory(
-    rng: &mut u32,
+fn carve_territory<R: BoardRng>(
+    rng: &mut R,
     stones: &mut [u8],
     territory: &mut [u8],
     color: u8,
@@ -111,7 +140,7 @@ fn carve_territory(
 
                 let surrounded = n && s && w && e && nw && ne && sw && se;
 
-                if surrounded && rand_chance_1_in(rng, chance_denominator) {
+                if surrounded && rng.chance_1_in(chance_denominator) {
                     stones[i] = 0;
                     territory[i] = color;
                     changed += 1;
@@ -293,6 +322,126 @@ fn fill_touching_territories_with_stones(stones: &mut [u8], territory: &mut [u8]
     changed_any
 }
 
+fn is_neutral_point(stones: &[u8], territory: &[u8], i: usize) -> bool {
+    stones[i] == 0 && territory[i] == 0
+}
+
+fn leave_neutral_points<R: BoardRng>(
+    rng: &mut R,
+    stones: &mut [u8],
+    territory: &[u8],
+    count: usize,
+) {
+    // 黒石と白石が接している境界の石のうち、地に接していないものを中立点の候補にする。
+    // （地に隣接する空点を作ると、その地が閉じなくなるため）
+    let mut cands: Vec<usize> = Vec::new();
+    for y in 0..BOARD_SIZE {
+        for x in 0..BOARD_SIZE {
+            let i = idx2(BOARD_SIZE, x, y);
+            let color = stones[i];
+            if color == 0 || territory[i] != 0 {
+                continue;
+            }
+
+            let mut touches_opp = false;
+            let mut touches_territory = false;
+            for (dx, dy) in NEIGH4 {
+                let nx = x as isize + dx;
+                let ny = y as isize + dy;
+                if nx < 0 || ny < 0 || nx >= BOARD_SIZE as isize || ny >= BOARD_SIZE as isize {
+                    continue;
+                }
+                let ni = idx2(BOARD_SIZE, nx as usize, ny as usize);
+                if stones[ni] != 0 && stones[ni] != color {
+                    touches_opp = true;
+                }
+                if territory[ni] != 0 {
+                    touches_territory = true;
+                }
+            }
+            if touches_opp && !touches_territory {
+                cands.push(i);
+            }
+        }
+    }
+
+    let mut placed = 0usize;
+    while placed < count && !cands.is_empty() {
+        let k = rng.below(cands.len() as u32) as usize;
+        let i = cands.swap_remove(k);
+
+        // 中立点同士が隣接すると境界が途切れてしまうので、既存の中立点の隣は避ける
+        let x = (i % BOARD_SIZE) as isize;
+        let y = (i / BOARD_SIZE) as isize;
+        let next_to_neutral = NEIGH4.iter().any(|&(dx, dy)| {
+            let nx = x + dx;
+            let ny = y + dy;
+            nx >= 0
+                && ny >= 0
+                && nx < BOARD_SIZE as isize
+                && ny < BOARD_SIZE as isize
+                && is_neutral_point(
+                    stones,
+                    territory,
+                    idx2(BOARD_SIZE, nx as usize, ny as usize),
+                )
+        });
+        if next_to_neutral {
+            continue;
+        }
+
+        stones[i] = 0;
+        placed += 1;
+    }
+}
+
+fn plant_dead_stones<R: BoardRng>(rng: &mut R, stones: &mut [u8], territory: &[u8], count: usize) {
+    // 十分大きな地の連結成分の中に相手色の石を置く。
+    // 相手の生きた石に接する位置は死石に見えないので避ける。
+    let mut remaining = count;
+    for color in [1u8, 2u8] {
+        let opp = if color == 1 { 2 } else { 1 };
+        for region in collect_territory_regions(territory, BOARD_SIZE, color) {
+            if remaining == 0 {
+                return;
+            }
+            if region.len() < DEAD_STONE_MIN_COMPONENT_SIZE {
+                continue;
+            }
+
+            let mut interior: Vec<usize> = region
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    let x = (i % BOARD_SIZE) as isize;
+                    let y = (i / BOARD_SIZE) as isize;
+                    NEIGH4.iter().all(|&(dx, dy)| {
+                        let nx = x + dx;
+                        let ny = y + dy;
+                        nx < 0
+                            || ny < 0
+                            || nx >= BOARD_SIZE as isize
+                            || ny >= BOARD_SIZE as isize
+                            || stones[idx2(BOARD_SIZE, nx as usize, ny as usize)] != opp
+                    })
+                })
+                .collect();
+            if interior.is_empty() {
+                continue;
+            }
+
+            let cap = (region.len() / DEAD_STONE_CELLS_PER_STONE).min(remaining);
+            let planted = 1 + rng.below(cap as u32) as usize;
+            for _ in 0..planted.min(interior.len()) {
+                let k = rng.below(interior.len() as u32) as usize;
+                let i = interior.swap_remove(k);
+                stones[i] = opp;
+                remaining -= 1;
+            }
+        }
+    }
+}
+
 /// 盤面状態を生成する
 ///
 /// Perlinノイズを用いて自然な盤面を生成します。
@@ -303,8 +452,122 @@ fn fill_touching_territories_with_stones(stones: &mut [u8], territory: &mut [u8]
 /// # 戻り値
 /// 生成された盤面状態を返します。
 pub fn generate_board_state(seed: u32) -> BoardState {
-    let mut rng = seed ^ SEED_XOR_MASK;
+    generate_board_state_with_options(seed, &GenerateOptions::default())
+}
 
+/// オプションを指定して盤面状態を生成する
+///
+/// `options` が既定値のときは [`generate_board_state`] と同じ盤面になります。
+/// `options.ensure_levelable` が有効なら [`generate_levelable_board_state`] の盤面を返します。
+///
+/// # 引数
+/// - `seed`: 乱数シード
+/// - `options`: 生成オプション
+///
+/// # 戻り値
+/// 生成された盤面状態を返します。
+pub fn generate_board_state_with_options(seed: u32, options: &GenerateOptions) -> BoardState {
+    if options.ensure_levelable {
+        return generate_levelable_board_state(&seed.into(), options).0;
+    }
+    generate_board_state_u32(seed, options)
+}
+
+fn generate_board_state_u32(seed: u32, options: &GenerateOptions) -> BoardState {
+    let mut rng = XorShift32::new(seed ^ SEED_XOR_MASK);
+    generate_board_state_with_rng(&mut rng, seed ^ FBM_SEED_XOR, seed.into(), options)
+}
+
+/// 数値または文字列のシードから盤面状態を生成する
+///
+/// `u32` に収まる数値シードは [`generate_board_state_with_options`] と同じ盤面になり、
+/// それ以外は既定の64bit RNG（[`SplitMix64`]）を使います。
+/// `"{seed}+fill3"` の形のシードは、`seed` の盤面の最も小さい地を3回埋めた盤面になります
+/// （[`generate_levelable_board_state`] が直した盤面）。
+/// `options.ensure_levelable` が有効なら [`generate_levelable_board_state`] の盤面を返します。
+///
+/// # 引数
+/// - `seed`: シード
+/// - `options`: 生成オプション
+///
+/// # 戻り値
+/// 生成された盤面状態を返します。
+pub fn generate_board_state_from_seed(seed: &Seed, options: &GenerateOptions) -> BoardState {
+    if options.ensure_levelable {
+        return generate_levelable_board_state(seed, options).0;
+    }
+    generate_unverified(seed, options)
+}
+
+fn generate_unverified(seed: &Seed, options: &GenerateOptions) -> BoardState {
+    match seed {
+        Seed::Number(n) => match u32::try_from(*n) {
+            Ok(seed32) => generate_board_state_u32(seed32, options),
+            Err(_) => generate_seeded_unverified::<SplitMix64>(*n, seed.clone(), options),
+        },
+        Seed::Text(s) => {
+            if let Some(n) = parse_decimal_seed(s) {
+                return generate_unverified(&Seed::Number(n), options);
+            }
+            if let Some((base, fills)) = parse_repaired_seed(s) {
+                let mut board = generate_unverified(&Seed::Text(base.to_string()), options);
+                for _ in 0..fills {
+                    fill_smallest_territory_region(&mut board);
+                }
+                board.seed = seed.clone();
+                return board;
+            }
+            generate_seeded_unverified::<SplitMix64>(seed_from_str(s), seed.clone(), options)
+        }
+    }
+}
+
+/// 任意の [`SeedableBoardRng`] で盤面状態を生成する
+///
+/// ノイズのシードは乱数列の最初の値から取ります。
+/// `options.ensure_levelable` が有効なら、整地を確かめて派生シードで作り直します
+/// （派生シードは文字列シードと同じく FNV-1a で64bitにします）。
+///
+/// # 引数
+/// - `seed64`: RNGの初期化に使う64bitシード
+/// - `seed`: 盤面に記録するシード
+/// - `options`: 生成オプション
+///
+/// # 戻り値
+/// 生成された盤面状態を返します。
+pub fn generate_board_state_seeded<R: SeedableBoardRng>(
+    seed64: u64,
+    seed: Seed,
+    options: &GenerateOptions,
+) -> BoardState {
+    if !options.ensure_levelable {
+        return generate_seeded_unverified::<R>(seed64, seed, options);
+    }
+    let generate = |s: &Seed| match s {
+        Seed::Text(text) if *s != seed => {
+            generate_seeded_unverified::<R>(seed_from_str(text), s.clone(), options)
+        }
+        _ => generate_seeded_unverified::<R>(seed64, s.clone(), options),
+    };
+    generate_levelable_with(&seed, options, generate).0
+}
+
+fn generate_seeded_unverified<R: SeedableBoardRng>(
+    seed64: u64,
+    seed: Seed,
+    options: &GenerateOptions,
+) -> BoardState {
+    let mut rng = R::from_seed(seed64);
+    let noise_seed = rng.next_u32();
+    generate_board_state_with_rng(&mut rng, noise_seed, seed, options)
+}
+
+fn generate_board_state_with_rng<R: BoardRng>(
+    rng: &mut R,
+    noise_seed: u32,
+    seed: Seed,
+    options: &GenerateOptions,
+) -> BoardState {
     // 1) まず盤面を黒石/白石で埋める（空は作らない）: パーリンノイズで塊を作る
     let mut stones = vec![0u8; BOARD_SIZE * BOARD_SIZE];
     let mut territory = vec![0u8; BOARD_SIZE * BOARD_SIZE];
@@ -312,14 +575,7 @@ pub fn generate_board_state(seed: u32) -> BoardState {
         for x in 0..BOARD_SIZE {
             let fx = x as f32 / FBM_SCALE;
             let fy = y as f32 / FBM_SCALE;
-            let n = fbm2(
-                seed ^ FBM_SEED_XOR,
-                fx,
-                fy,
-                FBM_OCTAVES,
-                FBM_LACUNARITY,
-                FBM_GAIN,
-            );
+            let n = fbm2(noise_seed, fx, fy, FBM_OCTAVES, FBM_LACUNARITY, FBM_GAIN);
             stones[idx2(BOARD_SIZE, x, y)] = if n >= 0.0 { 1 } else { 2 };
         }
     }
@@ -327,7 +583,7 @@ pub fn generate_board_state(seed: u32) -> BoardState {
     // 2) 地化（少しマイルド）
     for color in [1u8, 2u8] {
         carve_territory(
-            &mut rng,
+            rng,
             &mut stones,
             &mut territory,
             color,
@@ -346,10 +602,132 @@ pub fn generate_board_state(seed: u32) -> BoardState {
         }
     }
 
+    // 5) 黒白の境界に中立点（ダメ）を残す
+    if options.neutral_points > 0 {
+        leave_neutral_points(rng, &mut stones, &territory, options.neutral_points);
+    }
+
+    // 6) 大きな地に死石を置く
+    if options.dead_stones > 0 {
+        plant_dead_stones(rng, &mut stones, &territory, options.dead_stones);
+    }
+
+    // 7) アゲハマ
+    let mut prisoners = [0u32; 2];
+    if options.max_prisoners > 0 {
+        for p in prisoners.iter_mut() {
+            *p = rng.below(options.max_prisoners + 1);
+        }
+    }
+
     BoardState {
         size: BOARD_SIZE as u32,
         seed,
         stones,
         territory,
+        prisoners,
     }
 }
+
+fn fill_smallest_territory_region(board: &mut BoardState) -> bool {
+    let mut smallest: Option<(u8, Vec<usize>)> = None;
+    for color in [1u8, 2u8] {
+        for region in collect_territory_regions(&board.territory, BOARD_SIZE, color) {
+            if smallest
+                .as_ref()
+                .is_none_or(|(_, r)| region.len() < r.len())
+            {
+                smallest = Some((color, region));
+            }
+        }
+    }
+    let Some((color, region)) = smallest else {
+        return false;
+    };
+    for i in region {
+        board.stones[i] = color;
+        board.territory[i] = 0;
+    }
+    true
+}
+
+/// 作り直すときのシード（元のシードに `#1`, `#2`, ... を付けた文字列シード）
+fn derived_seed(seed: &Seed, attempt: usize) -> Seed {
+    match seed {
+        Seed::Number(n) => Seed::Text(format!("{n}#{attempt}")),
+        Seed::Text(s) => Seed::Text(format!("{s}#{attempt}")),
+    }
+}
+
+/// 元の盤面の小さい地を `fills` 回埋めて直した盤面のシード（`"{seed}+fill{fills}"`）
+fn repaired_seed(seed: &Seed, fills: usize) -> Seed {
+    match seed {
+        Seed::Number(n) => Seed::Text(format!("{n}{REPAIR_SEED_MARK}{fills}")),
+        Seed::Text(s) => Seed::Text(format!("{s}{REPAIR_SEED_MARK}{fills}")),
+    }
+}
+
+/// `"{seed}+fill{fills}"` を元のシードと埋めた回数に分ける
+fn parse_repaired_seed(s: &str) -> Option<(&str, usize)> {
+    let (base, fills) = s.rsplit_once(REPAIR_SEED_MARK)?;
+    if !fills.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let fills: usize = fills.parse().ok()?;
+    (fills > 0).then_some((base, fills))
+}
+
+fn generate_levelable_with(
+    seed: &Seed,
+    options: &GenerateOptions,
+    generate: impl Fn(&Seed) -> BoardState,
+) -> (BoardState, bool) {
+    let is_levelable =
+        |board: &BoardState| level_board_with_options(board.clone(), &options.level, None).is_ok();
+
+    let original = generate(seed);
+    if is_levelable(&original) {
+        return (original, true);
+    }
+    for attempt in 1..=LEVELABLE_SEED_ATTEMPTS {
+        let board = generate(&derived_seed(seed, attempt));
+        if is_levelable(&board) {
+            return (board, true);
+        }
+    }
+
+    // どの派生シードでも整地できなければ、元の盤面の小さい地を埋めて直す
+    let mut board = original.clone();
+    for fills in 1..=LEVELABLE_REPAIR_ATTEMPTS {
+        if !fill_smallest_territory_region(&mut board) {
+            break;
+        }
+        if is_levelable(&board) {
+            board.seed = repaired_seed(seed, fills);
+            return (board, true);
+        }
+    }
+    (original, false)
+}
+
+/// 整地できることを確認した盤面状態を生成する
+///
+/// 生成した盤面を `options.level` で実際に整地してみて、失敗した場合は派生シード
+/// （`"{seed}#1"` から `"{seed}#4"` までの文字列シード）で作り直します。どれも整地できなければ、
+/// 元の盤面の最も小さい地の連結成分をその色の石で埋めて再試行します（最大8回）。
+/// 埋めた盤面の `seed` は元のシードに `+fill` と埋めた回数を付けた文字列シード（`"{seed}+fill3"`）です。
+/// 返す盤面の `seed` を `ensure_levelable` なしで [`generate_board_state_from_seed`] に渡すと、同じ盤面になります。
+///
+/// # 引数
+/// - `seed`: シード
+/// - `options`: 生成オプション
+///
+/// # 戻り値
+/// 盤面状態と、整地できることを確認できたかどうかを返します。
+/// 確認できなかった場合は元のシードの盤面をそのまま返します。
+pub fn generate_levelable_board_state(
+    seed: &Seed,
+    options: &GenerateOptions,
+) -> (BoardState, bool) {
+    generate_levelable_with(seed, options, |s| generate_unverified(s, options))
+}
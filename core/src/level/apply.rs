@@ -1,21 +1,30 @@
+use crate::level::constraints::Constraints;
+use crate::level::patterns::{PatternSpec, cell_in_pattern};
 use crate::types::idx2;
+use std::sync::Arc;
 
 pub(crate) fn apply_rects_and_fill(
     size: usize,
     stones: &mut [u8],
     territory: &mut [u8],
     color: u8,
-    rects: &[(usize, usize, crate::level::patterns::PatternSpec)],
+    rects: &[(usize, usize, Arc<PatternSpec>)],
     used: &[bool],
+    constraints: &Constraints,
 ) {
-    for &(x, y, spec) in rects {
+    for (x, y, spec) in rects {
+        let (x, y) = (*x, *y);
         // specに従って適用
         for dy in 0..spec.h {
             for dx in 0..spec.w {
-                if !crate::level::patterns::cell_in_pattern(dx, dy, &spec) {
+                if !cell_in_pattern(dx, dy, spec) {
                     continue;
                 }
                 let i = idx2(size, x + dx, y + dy);
+                // 動かさない石はそのまま残す
+                if constraints.locked.contains(i) {
+                    continue;
+                }
                 if spec
                     .anchor_cells
                     .iter()
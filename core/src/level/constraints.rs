@@ -0,0 +1,64 @@
+use crate::level::options::LevelOptions;
+use crate::level::patterns::{Bitboard, Cand};
+use crate::types::idx2;
+
+/// 整地の制約（動かさない石と、パターンを置かないセル）
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Constraints {
+    pub(crate) locked: Bitboard,
+    pub(crate) forbidden: Bitboard,
+}
+
+impl Constraints {
+    /// オプションの `locked` / `forbidden` を盤面に合わせて検証し、セルの集合にする
+    ///
+    /// 動かさないセルには石がなければなりません（死石は取り上げた後の盤面で判定します）。
+    pub(crate) fn new(size: usize, stones: &[u8], options: &LevelOptions) -> Result<Self, String> {
+        let mut constraints = Constraints::default();
+        for &(x, y) in &options.locked {
+            let (x, y) = (x as usize, y as usize);
+            if x >= size || y >= size {
+                return Err(format!("locked cell ({x}, {y}) is outside the board"));
+            }
+            let i = idx2(size, x, y);
+            if stones[i] == 0 {
+                return Err(format!("locked cell ({x}, {y}) has no stone"));
+            }
+            constraints.locked.set(i);
+        }
+        for r in &options.forbidden {
+            let (x, y, w, h) = (r.x as usize, r.y as usize, r.w as usize, r.h as usize);
+            if w == 0 || h == 0 {
+                return Err(format!("forbidden area at ({x}, {y}) is empty"));
+            }
+            if x + w > size || y + h > size {
+                return Err(format!(
+                    "forbidden area ({x}, {y}, {w}x{h}) is outside the board"
+                ));
+            }
+            for cy in y..y + h {
+                for cx in x..x + w {
+                    constraints.forbidden.set(idx2(size, cx, cy));
+                }
+            }
+        }
+        Ok(constraints)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.locked.is_empty() && self.forbidden.is_empty()
+    }
+
+    /// 候補が制約を守るか
+    ///
+    /// 禁止セルにかからず、動かさない石にはアンカーとして（石を残して）だけかかること。
+    pub(crate) fn allows(&self, size: usize, stones: &[u8], c: &Cand) -> bool {
+        if c.mask.intersects(&self.forbidden) {
+            return false;
+        }
+        (c.mask & self.locked).cells().all(|i| {
+            let (dx, dy) = (i % size - c.x, i / size - c.y);
+            stones[i] == c.color && c.spec.anchor_cells.contains(&(dx, dy))
+        })
+    }
+}
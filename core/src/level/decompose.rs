@@ -0,0 +1,531 @@
+use crate::level::ip::{
+    SelectTarget, build_problem, canonical_ranks, describe_targets, log_solution,
+};
+use crate::level::options::LevelOptions;
+use crate::level::outcome::{RegionOutcome, SolveInfo, SolveStatus};
+use crate::level::patterns::{Bitboard, Cand, PatternSlot};
+use crate::level::solver::{
+    Deadline, SelectProblem, canonicalize, is_infeasible, is_stopped, select_solver,
+};
+use crate::types::{Logger, cell_coords, collect_territory_regions};
+use std::collections::BTreeMap;
+use std::thread;
+
+// 部分問題の数の上限（超える場合は分けずに解く）
+const MAX_REGION_JOBS: usize = 256;
+
+/// 領域に分けて解いた結果（選んだ候補、ソルバーの実行結果、領域ごとの結果）
+pub(crate) type RegionSolution = (Vec<usize>, SolveInfo, Vec<RegionOutcome>);
+
+/// 互いに影響しない候補のまとまり（領域）
+struct Block {
+    /// 候補の添字（昇順）
+    members: Vec<usize>,
+    /// 色ごと（`targets` と同じ順）の地のセル
+    cells: Vec<Vec<usize>>,
+    /// グループごとに割り当てる目標の候補（昇順）
+    options: Vec<Vec<usize>>,
+}
+
+/// 部分問題の解（選んだ候補とソルバーの実行結果）。解がない・打ち切りまでに見つからなければ None
+type JobResult = Option<(Vec<usize>, SolveInfo)>;
+
+// 目的関数が同じとみなす差（コストの差はこれよりずっと大きい）
+const TIE_EPS: f64 = 1e-6;
+
+/// 組合せの値（目的関数と、同じ値の組合せを比べる順位の合計・順位を昇順に並べた列）
+///
+/// 全体を1つのモデルで解いた場合の `canonicalize` と同じ順に比べるので、
+/// 順位を使うときは同じ解を選びます。順位を使わなければ目的関数だけで比べます。
+#[derive(Clone, Default)]
+struct Score {
+    objective: f64,
+    rank_sum: u64,
+    key: Vec<u32>,
+}
+
+impl Score {
+    /// 解いた部分問題の値
+    fn solved(objective: f64, ranks: Option<&[u32]>, picked: &[usize]) -> Self {
+        let mut key: Vec<u32> = ranks
+            .map(|ranks| picked.iter().map(|&i| ranks[i]).collect())
+            .unwrap_or_default();
+        key.sort_unstable();
+        Score {
+            objective,
+            rank_sum: key.iter().map(|&r| r as u64).sum(),
+            key,
+        }
+    }
+
+    /// まだ解いていない部分問題の値（順位は最小とみなし、同じ目的関数なら先に解かせる）
+    fn bound(objective: f64) -> Self {
+        Score {
+            objective,
+            ..Score::default()
+        }
+    }
+
+    fn add(&self, other: &Score) -> Score {
+        let mut key = [self.key.as_slice(), other.key.as_slice()].concat();
+        key.sort_unstable();
+        Score {
+            objective: self.objective + other.objective,
+            rank_sum: self.rank_sum + other.rank_sum,
+            key,
+        }
+    }
+
+    fn better_than(&self, other: &Score) -> bool {
+        if (self.objective - other.objective).abs() > TIE_EPS {
+            return self.objective < other.objective;
+        }
+        (self.rank_sum, &self.key) < (other.rank_sum, &other.key)
+    }
+}
+
+/// 部分問題（領域と、グループごとの目標）
+struct Job {
+    block: usize,
+    targets: Vec<usize>,
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    if a != b {
+        parent[a.max(b)] = a.min(b);
+    }
+}
+
+/// 候補を、衝突する・同じ地の連結成分にかかる候補どうしでまとめる
+fn split_blocks(
+    size: usize,
+    territory: &[u8],
+    cands: &[Cand],
+    targets: &[SelectTarget],
+    problem: &SelectProblem,
+) -> Vec<Block> {
+    let m = cands.len();
+    // 候補 0..m と地の連結成分 m.. を節点にする
+    let mut regions: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut region_of = vec![usize::MAX; size * size];
+    for (t, target) in targets.iter().enumerate() {
+        for region in collect_territory_regions(territory, size, target.color) {
+            for &c in &region {
+                region_of[c] = regions.len();
+            }
+            regions.push((t, region));
+        }
+    }
+    let mut parent: Vec<usize> = (0..m + regions.len()).collect();
+    for q in &problem.cliques {
+        for &i in &q[1..] {
+            union(&mut parent, q[0], i);
+        }
+    }
+    for (i, c) in cands.iter().enumerate() {
+        for cell in c.mask.cells() {
+            if region_of[cell] != usize::MAX {
+                union(&mut parent, i, m + region_of[cell]);
+            }
+        }
+    }
+
+    let mut by_root: BTreeMap<usize, Block> = BTreeMap::new();
+    for i in 0..m {
+        let root = find(&mut parent, i);
+        by_root
+            .entry(root)
+            .or_insert_with(|| Block {
+                members: Vec::new(),
+                cells: vec![Vec::new(); targets.len()],
+                options: Vec::new(),
+            })
+            .members
+            .push(i);
+    }
+    for (r, (t, region)) in regions.into_iter().enumerate() {
+        let root = find(&mut parent, m + r);
+        if let Some(block) = by_root.get_mut(&root) {
+            block.cells[t].extend(region);
+        }
+    }
+    let mut blocks: Vec<Block> = by_root.into_values().collect();
+    for block in &mut blocks {
+        for cells in &mut block.cells {
+            cells.sort_unstable();
+        }
+    }
+    blocks
+}
+
+/// 領域ごとに、グループに割り当てる目標の候補を決める
+///
+/// 主パターンは点数以上のセルを覆うので、領域の主パターンの候補が覆えるセル数を単位で割った数が
+/// その領域の点数の上限になります。他の領域の上限の合計で目標に届かない分が下限で、
+/// その間の点数をすべて単位刻みで試すので、分けずに解いた場合と同じ解が候補に含まれます。
+fn assign_options(cands: &[Cand], targets: &[SelectTarget], blocks: &mut [Block]) {
+    for target in targets {
+        let goal = target.main_points / target.unit;
+        let caps: Vec<usize> = blocks
+            .iter()
+            .map(|block| {
+                let mut union = Bitboard::default();
+                for &i in &block.members {
+                    let c = &cands[i];
+                    if c.color == target.color && c.spec.slot == PatternSlot::Main {
+                        union |= c.mask;
+                    }
+                }
+                (union.count() / target.unit).min(goal)
+            })
+            .collect();
+        let total: usize = caps.iter().sum();
+        for (block, &cap) in blocks.iter_mut().zip(&caps) {
+            let lo = goal.saturating_sub(total - cap).min(cap);
+            block
+                .options
+                .push((lo..=cap).map(|k| k * target.unit).collect());
+
+            let has_rem = block.members.iter().any(|&i| {
+                cands[i].color == target.color
+                    && matches!(cands[i].spec.slot, PatternSlot::Remainder(_))
+            });
+            let rem = if target.remainder > 0 && has_rem {
+                vec![0, target.remainder]
+            } else {
+                vec![0]
+            };
+            block.options.push(rem);
+        }
+    }
+}
+
+/// 領域の目標の組をすべて並べる（グループ順の直積）
+fn combinations(options: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut combos: Vec<Vec<usize>> = vec![Vec::new()];
+    for values in options {
+        combos = combos
+            .into_iter()
+            .flat_map(|prefix| {
+                values.iter().map(move |&v| {
+                    let mut c = prefix.clone();
+                    c.push(v);
+                    c
+                })
+            })
+            .collect();
+    }
+    combos
+}
+
+/// 部分問題の下界（個数の制約だけの線形緩和）。個数を満たせなければ None
+fn lower_bound(problem: &SelectProblem, members: &[usize], goals: &[usize]) -> Option<f64> {
+    let mut bound = 0.0;
+    for (group, &goal) in problem.groups.iter().zip(goals) {
+        let mut items: Vec<(f64, f64)> = group
+            .members
+            .iter()
+            .filter(|&&(i, w)| w > 0.0 && members.binary_search(&i).is_ok())
+            .map(|&(i, w)| (problem.costs[i], w))
+            .collect();
+        items.sort_by(|a, b| (a.0 / a.1).total_cmp(&(b.0 / b.1)));
+        let mut rest = goal as f64;
+        for (cost, w) in items {
+            if rest <= 0.0 {
+                break;
+            }
+            let take = w.min(rest);
+            bound += cost * take / w;
+            rest -= take;
+        }
+        if rest > 0.0 {
+            return None;
+        }
+    }
+    Some(bound)
+}
+
+/// 目標の合計が `goal` に一致する、値（`Score`）が最小の組合せを動的計画法で選ぶ
+///
+/// `value` は部分問題の値（解がなければ None）。領域ごとに選んだ部分問題（何も選ばなければ None）を返します。
+fn best_allocation(
+    jobs: &[Job],
+    blocks: usize,
+    goal: &[usize],
+    value: impl Fn(usize) -> Option<Score>,
+) -> Option<Vec<Option<usize>>> {
+    // グループごとの目標の合計 → (値, 領域ごとに選んだ部分問題)
+    let mut states: BTreeMap<Vec<usize>, (Score, Vec<Option<usize>>)> = BTreeMap::new();
+    states.insert(vec![0; goal.len()], (Score::default(), Vec::new()));
+    for b in 0..blocks {
+        // 何も選ばない組と、解がありうる部分問題
+        let mut choices: Vec<(Option<usize>, Score)> = vec![(None, Score::default())];
+        for (j, job) in jobs.iter().enumerate() {
+            if job.block == b
+                && let Some(v) = value(j)
+            {
+                choices.push((Some(j), v));
+            }
+        }
+        let mut next: BTreeMap<Vec<usize>, (Score, Vec<Option<usize>>)> = BTreeMap::new();
+        for (state, (score, picked)) in &states {
+            for (j, c) in &choices {
+                let sum: Vec<usize> = match *j {
+                    Some(j) => state
+                        .iter()
+                        .zip(&jobs[j].targets)
+                        .map(|(a, b)| a + b)
+                        .collect(),
+                    None => state.clone(),
+                };
+                if sum.iter().zip(goal).any(|(s, g)| s > g) {
+                    continue;
+                }
+                let total = score.add(c);
+                if next
+                    .get(&sum)
+                    .is_some_and(|(best, _)| !total.better_than(best))
+                {
+                    continue;
+                }
+                let mut picked = picked.clone();
+                picked.push(*j);
+                next.insert(sum, (total, picked));
+            }
+        }
+        states = next;
+    }
+    states.remove(goal).map(|(_, chosen)| chosen)
+}
+
+/// 部分問題を並列に解いた結果（部分問題ごとの解と、打ち切りで解けなかった部分問題があったか）
+type JobResults = (Vec<(usize, JobResult)>, bool);
+
+/// 部分問題を並列に解く（スレッドごとに飛び飛びに分担する）
+///
+/// `ranks` があれば、部分問題の解を同じコストの解のうち順位で決まる1つに選び直します。
+/// 解がない部分問題と、打ち切りまでに解が見つからなかった部分問題は `None` になります。
+/// ソルバーの失敗やスレッドの panic は `Err` を返します。
+fn solve_jobs(
+    problem: &SelectProblem,
+    ranks: Option<&[u32]>,
+    blocks: &[Block],
+    jobs: &[Job],
+    pending: &[usize],
+    options: &LevelOptions,
+    deadline: Deadline,
+) -> Result<JobResults, String> {
+    let threads = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(pending.len());
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                scope.spawn(move || -> Result<JobResults, String> {
+                    let backend = select_solver(options.solver.backend)?;
+                    let mut results = Vec::new();
+                    let mut stopped = false;
+                    for &j in pending.iter().skip(t).step_by(threads) {
+                        let members = &blocks[jobs[j].block].members;
+                        let goals: Vec<f64> = jobs[j].targets.iter().map(|&v| v as f64).collect();
+                        let sub = problem.restrict(members, &goals);
+                        let result =
+                            match backend.solve(&sub, &deadline.solver_options(&options.solver)) {
+                                Ok((mut picked, mut info)) => {
+                                    if let Some(ranks) = ranks {
+                                        let ranks: Vec<u32> =
+                                            members.iter().map(|&i| ranks[i]).collect();
+                                        picked = canonicalize(
+                                            backend.as_ref(),
+                                            &sub,
+                                            &ranks,
+                                            picked,
+                                            &options.solver,
+                                            deadline,
+                                        );
+                                    }
+                                    info.objective = sub.objective(&picked);
+                                    Some((picked.into_iter().map(|i| members[i]).collect(), info))
+                                }
+                                Err(e) if is_infeasible(&e) => None,
+                                Err(e) if is_stopped(&e) => {
+                                    stopped = true;
+                                    None
+                                }
+                                Err(e) => return Err(e),
+                            };
+                        results.push((j, result));
+                    }
+                    Ok((results, stopped))
+                })
+            })
+            .collect();
+        let mut results = Vec::new();
+        let mut stopped = false;
+        for h in handles {
+            let (part, s) = h
+                .join()
+                .map_err(|_| "region solver worker panicked".to_string())??;
+            results.extend(part);
+            stopped |= s;
+        }
+        Ok((results, stopped))
+    })
+}
+
+/// 互いに影響しない領域に分けて候補を選ぶ
+///
+/// 候補が重なる・接する・同じ地の連結成分にかかることでつながった地を1つの領域とし、
+/// 領域ごとに主パターン・端数パターンの数を変えた部分問題を作り、目標の合計が一致する組合せのうち
+/// 目的関数が最小のものを動的計画法で選びます。部分問題は、組合せに選ばれたものだけを並列に解きます。
+///
+/// 領域が1つしかない場合、部分問題が多すぎる場合、組合せが見つからない場合は `None` を返します
+/// （呼び出し側で全体を1つのモデルで解く）。端数を複数のパターンに分けてよい場合も、
+/// パターンの数の上限が領域をまたぐので `None` を返します。
+pub(crate) fn solve_by_regions(
+    size: usize,
+    territory: &[u8],
+    cands: &[Cand],
+    targets: &[SelectTarget],
+    options: &LevelOptions,
+    deadline: Deadline,
+    logger: Option<&dyn Logger>,
+) -> Result<Option<RegionSolution>, String> {
+    if targets.iter().any(|t| t.splits_remainder()) {
+        return Ok(None);
+    }
+    let backend = select_solver(options.solver.backend)?;
+    let problem = build_problem(size, cands, targets, options.objective);
+    let ranks = options.deterministic.then(|| canonical_ranks(cands));
+    let mut blocks = split_blocks(size, territory, cands, targets, &problem);
+    if blocks.len() <= 1 {
+        return Ok(None);
+    }
+    assign_options(cands, targets, &mut blocks);
+
+    // 何も選ばない組（目的関数 0）は解かずに済む
+    let mut jobs: Vec<Job> = Vec::new();
+    for (b, block) in blocks.iter().enumerate() {
+        for targets in combinations(&block.options) {
+            if targets.iter().any(|&v| v > 0) {
+                jobs.push(Job { block: b, targets });
+            }
+        }
+    }
+    if jobs.len() > MAX_REGION_JOBS {
+        return Ok(None);
+    }
+    if let Some(l) = logger {
+        l.log(&format!(
+            "[regions] {} regions={} jobs={} {}",
+            backend.name(),
+            blocks.len(),
+            jobs.len(),
+            describe_targets(targets)
+        ));
+    }
+
+    // 下界で組合せを選び、まだ解いていない部分問題を解いて選び直す
+    // （選んだ組合せの部分問題がすべて解けているなら、それが最適。順位を使う場合は、
+    // 目的関数が同じになりうる組合せの部分問題もすべて解いてから、全体のモデルと同じ順で選ぶ）
+    let goal: Vec<usize> = targets
+        .iter()
+        .flat_map(|t| [t.main_points, t.remainder])
+        .collect();
+    let bounds: Vec<Option<f64>> = jobs
+        .iter()
+        .map(|job| lower_bound(&problem, &blocks[job.block].members, &job.targets))
+        .collect();
+    let mut solved: Vec<Option<JobResult>> = vec![None; jobs.len()];
+    // 打ち切りで解けなかった部分問題があれば、選んだ組合せが最適とは限らない
+    let mut stopped = false;
+    let chosen = loop {
+        let value = |j: usize| match &solved[j] {
+            Some(result) => result
+                .as_ref()
+                .map(|(picked, info)| Score::solved(info.objective, ranks.as_deref(), picked)),
+            None => bounds[j].map(Score::bound),
+        };
+        let Some(chosen) = best_allocation(&jobs, blocks.len(), &goal, value) else {
+            return Ok(None);
+        };
+        let pending: Vec<usize> = chosen
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|&j| solved[j].is_none())
+            .collect();
+        if pending.is_empty() {
+            break chosen;
+        }
+        let (results, s) = solve_jobs(
+            &problem,
+            ranks.as_deref(),
+            &blocks,
+            &jobs,
+            &pending,
+            options,
+            deadline,
+        )?;
+        stopped |= s;
+        for (j, result) in results {
+            solved[j] = Some(result);
+        }
+        // 結果のない部分問題は解けなかったものとして扱い、同じ組合せを選び続けないようにする
+        for &j in &pending {
+            solved[j].get_or_insert(None);
+        }
+    };
+    if let Some(l) = logger {
+        l.log(&format!(
+            "[regions] solved={}",
+            solved.iter().filter(|r| r.is_some()).count()
+        ));
+    }
+
+    let groups = goal.len();
+    let mut picked: Vec<usize> = Vec::new();
+    let mut info = SolveInfo::default();
+    let mut regions: Vec<RegionOutcome> = Vec::new();
+    for (b, j) in chosen.into_iter().enumerate() {
+        let (goals, solve) =
+            match j.and_then(|j| solved[j].as_ref().and_then(|r| r.as_ref()).map(|s| (j, s))) {
+                Some((j, (p, solve))) => {
+                    picked.extend(p);
+                    info.merge(solve);
+                    (jobs[j].targets.clone(), solve.clone())
+                }
+                None => (vec![0; groups], SolveInfo::default()),
+            };
+        for (t, target) in targets.iter().enumerate() {
+            if blocks[b].cells[t].is_empty() && goals[2 * t] == 0 && goals[2 * t + 1] == 0 {
+                continue;
+            }
+            regions.push(RegionOutcome {
+                color: target.color,
+                cells: cell_coords(size, blocks[b].cells[t].iter().copied()),
+                main_points: goals[2 * t],
+                remainder: goals[2 * t + 1] > 0,
+                solve: solve.clone(),
+            });
+        }
+    }
+    picked.sort_unstable();
+    if stopped {
+        info.status = info.status.max(SolveStatus::TimeLimit);
+    }
+
+    if let Some(l) = logger {
+        log_solution(l, backend.name(), cands, &picked, &info);
+    }
+    Ok(Some((picked, info, regions)))
+}
@@ -0,0 +1,372 @@
+use crate::level::constraints::Constraints;
+use crate::level::ip::{conflict_cliques, pattern_points};
+use crate::level::options::AlignmentOptions;
+use crate::level::patterns::{Bitboard, Cand, PatternSlot, PatternSpec, generate_candidates};
+use crate::level::select::ColorTarget;
+use crate::level::solver::{Deadline, Feasibility, SelectGroup, SelectProblem, check_feasibility};
+use crate::types::{cell_coords, collect_territory_regions};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+// 1回の実行可能性判定にかける時間の上限（秒）
+const DIAGNOSE_TIME_LIMIT: f64 = 1.0;
+
+/// 整地できない理由（座標は (x, y)）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Diagnostic {
+    /// 主パターンの候補が1つもない
+    NoMainCandidates { color: u8, main_points: usize },
+    /// 端数パターンの候補が1つもない
+    NoRemainderCandidates { color: u8, remainder: u8 },
+    /// 地の連結成分に、大きさに見合う点数の主パターンを置けない
+    RegionLacksMain {
+        color: u8,
+        /// 連結成分のセル
+        cells: Vec<(u32, u32)>,
+        /// セル数を単位で切り捨てた点数
+        needed: usize,
+        /// 衝突せずに置ける主パターンの点数の最大値
+        max_points: usize,
+        /// 連結成分にかかる主パターン候補の数
+        candidates: usize,
+    },
+    /// 主パターンどうしの衝突で、盤面全体で目標の点数を置けない
+    MainConflicts {
+        color: u8,
+        main_points: usize,
+        max_points: usize,
+        /// 最大の点数を置いたときに覆えない地のセル
+        cells: Vec<(u32, u32)>,
+    },
+    /// 主パターンを目標の点数置くと、どの端数パターンも衝突する
+    RemainderConflicts {
+        color: u8,
+        remainder: u8,
+        /// 端数パターン候補のセル
+        cells: Vec<(u32, u32)>,
+    },
+    /// 色ごとには置けるが、異なる色のパターンが接してしまう
+    ColorConflicts {
+        /// 異なる色の候補が接するセル
+        cells: Vec<(u32, u32)>,
+    },
+    /// 石を置き直すだけでは整地できない（パターンの点数の合計が、石のない地のセル数と違う）
+    StonesNotConserved {
+        color: u8,
+        /// 主パターンと端数パターンの点数の合計
+        points: usize,
+        /// 石のない地のセル数
+        empty_territory: usize,
+        /// 同色の地として数えた同色の石（取り除くと埋め戻す石が足りない）
+        cells: Vec<(u32, u32)>,
+    },
+    /// 動かさない石・パターンを置かない範囲がなければ整地できる
+    ConstraintsInfeasible {
+        /// 制約なしで整地したときに、石が動く動かさないセルと地になる禁止セル
+        cells: Vec<(u32, u32)>,
+    },
+}
+
+/// 候補の一部だけを使った実行可能性判定
+///
+/// groups は (候補の添字, 点数の目標, 選べる数の上限) の組です。
+/// 1回の判定は `DIAGNOSE_TIME_LIMIT` 秒と `deadline` までの残り時間の短い方で打ち切ります。
+fn check_groups(
+    size: usize,
+    cands: &[Cand],
+    groups: &[(&[usize], usize, Option<usize>)],
+    deadline: Deadline,
+) -> Feasibility {
+    let mut members: Vec<usize> = groups
+        .iter()
+        .flat_map(|(m, _, _)| m.iter().copied())
+        .collect();
+    members.sort_unstable();
+    members.dedup();
+    let local = |i: usize| members.binary_search(&i).unwrap_or(0);
+
+    let problem = SelectProblem {
+        costs: vec![0.0; members.len()],
+        groups: groups
+            .iter()
+            .map(|&(m, target, max_picks)| SelectGroup {
+                members: m
+                    .iter()
+                    .map(|&i| (local(i), pattern_points(&cands[i])))
+                    .collect(),
+                target: target as f64,
+                max_picks,
+            })
+            .collect(),
+        cliques: conflict_cliques(size, cands, &members),
+        cells: members
+            .iter()
+            .map(|&i| cands[i].mask.cells().collect())
+            .collect(),
+        cuts: Vec::new(),
+        caps: Vec::new(),
+        objective_cap: None,
+    };
+    let time_limit = deadline
+        .remaining()
+        .map_or(DIAGNOSE_TIME_LIMIT, |t| t.min(DIAGNOSE_TIME_LIMIT));
+    match check_feasibility(&problem, time_limit) {
+        Feasibility::Feasible(picked) => {
+            Feasibility::Feasible(picked.into_iter().map(|i| members[i]).collect())
+        }
+        other => other,
+    }
+}
+
+/// 主パターンを衝突なく置ける点数の最大値（upto 以下、単位刻み）と、そのときの候補。
+/// 判定できなければ None
+fn max_points(
+    size: usize,
+    cands: &[Cand],
+    members: &[usize],
+    upto: usize,
+    unit: usize,
+    deadline: Deadline,
+) -> Option<(usize, Vec<usize>)> {
+    for k in (1..=upto / unit).rev().map(|k| k * unit) {
+        match check_groups(size, cands, &[(members, k, None)], deadline) {
+            Feasibility::Feasible(picked) => return Some((k, picked)),
+            Feasibility::Infeasible => continue,
+            Feasibility::Unknown => return None,
+        }
+    }
+    Some((0, Vec::new()))
+}
+
+/// 1色分の整地できない理由を調べる
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn diagnose_color(
+    size: usize,
+    stones: &[u8],
+    territory: &[u8],
+    target: ColorTarget,
+    specs: &[Arc<PatternSpec>],
+    alignment: &AlignmentOptions,
+    constraints: &Constraints,
+    deadline: Deadline,
+) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    if target.is_empty() {
+        return diags;
+    }
+    let color = target.color;
+
+    // どのパターンも取り除く石と残す地の数が点数と同じなので、石の数は点数の合計で決まる
+    if target.conserve_stones {
+        let empty_territory = (0..size * size)
+            .filter(|&i| territory[i] == color && stones[i] == 0)
+            .count();
+        let points = target.main_points + target.remainder as usize;
+        if points != empty_territory {
+            let cells = (0..size * size).filter(|&i| territory[i] == color && stones[i] == color);
+            diags.push(Diagnostic::StonesNotConserved {
+                color,
+                points,
+                empty_territory,
+                cells: cell_coords(size, cells),
+            });
+            return diags;
+        }
+    }
+
+    let cands = generate_candidates(
+        size,
+        stones,
+        territory,
+        color,
+        target.remainder,
+        target.pieces,
+        specs,
+        alignment,
+        constraints,
+        None,
+    );
+    let main: Vec<usize> = (0..cands.len())
+        .filter(|&i| cands[i].spec.slot == PatternSlot::Main)
+        .collect();
+    let rem: Vec<usize> = (0..cands.len())
+        .filter(|&i| matches!(cands[i].spec.slot, PatternSlot::Remainder(_)))
+        .collect();
+
+    if target.main_points > 0 && main.is_empty() {
+        diags.push(Diagnostic::NoMainCandidates {
+            color,
+            main_points: target.main_points,
+        });
+    }
+    if target.remainder > 0 && rem.is_empty() {
+        diags.push(Diagnostic::NoRemainderCandidates {
+            color,
+            remainder: target.remainder,
+        });
+    }
+    if !diags.is_empty() {
+        return diags;
+    }
+
+    // 連結成分ごとに、かかる主パターン候補だけで大きさに見合う点数を置けるか
+    for region in collect_territory_regions(territory, size, color) {
+        let needed = region.len() / target.unit * target.unit;
+        if needed == 0 {
+            continue;
+        }
+        let members: Vec<usize> = main
+            .iter()
+            .copied()
+            .filter(|&i| {
+                cands[i]
+                    .mask
+                    .cells()
+                    .any(|c| region.binary_search(&c).is_ok())
+            })
+            .collect();
+        if let Some((k, _)) = max_points(size, &cands, &members, needed, target.unit, deadline)
+            && k < needed
+        {
+            diags.push(Diagnostic::RegionLacksMain {
+                color,
+                cells: cell_coords(size, region),
+                needed,
+                max_points: k,
+                candidates: members.len(),
+            });
+        }
+    }
+    if !diags.is_empty() {
+        return diags;
+    }
+
+    // 盤面全体で主パターンを目標の点数置けるか
+    match check_groups(size, &cands, &[(&main, target.main_points, None)], deadline) {
+        Feasibility::Feasible(_) => {}
+        Feasibility::Infeasible => {
+            if let Some((k, picked)) = max_points(
+                size,
+                &cands,
+                &main,
+                target.main_points,
+                target.unit,
+                deadline,
+            ) {
+                let mut covered = vec![false; size * size];
+                for &i in &picked {
+                    for c in cands[i].mask.cells() {
+                        covered[c] = true;
+                    }
+                }
+                let uncovered = (0..size * size).filter(|&i| territory[i] == color && !covered[i]);
+                diags.push(Diagnostic::MainConflicts {
+                    color,
+                    main_points: target.main_points,
+                    max_points: k,
+                    cells: cell_coords(size, uncovered),
+                });
+            }
+            return diags;
+        }
+        Feasibility::Unknown => return diags,
+    }
+
+    // 主パターンと端数パターンを同時に置けるか
+    let pieces = (target.pieces > 1).then_some(target.pieces);
+    if target.remainder > 0
+        && matches!(
+            check_groups(
+                size,
+                &cands,
+                &[
+                    (&main, target.main_points, None),
+                    (&rem, target.remainder as usize, pieces)
+                ],
+                deadline
+            ),
+            Feasibility::Infeasible
+        )
+    {
+        let mut cells: Vec<usize> = rem.iter().flat_map(|&i| cands[i].mask.cells()).collect();
+        cells.sort_unstable();
+        cells.dedup();
+        diags.push(Diagnostic::RemainderConflicts {
+            color,
+            remainder: target.remainder,
+            cells: cell_coords(size, cells),
+        });
+    }
+    diags
+}
+
+/// 両色を同時に整地する場合の整地できない理由を調べる
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn diagnose_joint(
+    size: usize,
+    stones: &[u8],
+    territory: &[u8],
+    targets: &[ColorTarget],
+    specs: &[Arc<PatternSpec>],
+    alignment: &AlignmentOptions,
+    constraints: &Constraints,
+    deadline: Deadline,
+) -> Vec<Diagnostic> {
+    let diags: Vec<Diagnostic> = targets
+        .iter()
+        .flat_map(|&t| {
+            diagnose_color(
+                size,
+                stones,
+                territory,
+                t,
+                specs,
+                alignment,
+                constraints,
+                deadline,
+            )
+        })
+        .collect();
+    if !diags.is_empty() {
+        return diags;
+    }
+
+    // 色ごとには置けるので、異なる色の候補が接するセルを示す
+    let cands: Vec<Cand> = targets
+        .iter()
+        .filter(|t| !t.is_empty())
+        .flat_map(|t| {
+            generate_candidates(
+                size,
+                stones,
+                territory,
+                t.color,
+                t.remainder,
+                t.pieces,
+                specs,
+                alignment,
+                constraints,
+                None,
+            )
+        })
+        .collect();
+    let mut touching = Bitboard::default();
+    for a in 0..cands.len() {
+        for b in (a + 1)..cands.len() {
+            let (ca, cb) = (&cands[a], &cands[b]);
+            // 接するかどうかは片側の4近傍との重なりで決まる
+            if ca.color == cb.color || !ca.mask_block.intersects(&cb.mask) {
+                continue;
+            }
+            touching |= (ca.mask_block & cb.mask) | (cb.mask_block & ca.mask);
+        }
+    }
+    let cells: Vec<usize> = touching.cells().collect();
+    if !cells.is_empty() {
+        return vec![Diagnostic::ColorConflicts {
+            cells: cell_coords(size, cells),
+        }];
+    }
+    Vec::new()
+}
@@ -1,169 +1,459 @@
+use crate::level::options::{LevelOptions, Objective};
+use crate::level::outcome::SolveInfo;
 use crate::level::patterns::{
-    Cand, PatternSlot, log_patterns_enabled, long_edge_ok_2x5_only, mask_overlaps,
+    Cand, LongEdgeKey, PatternSlot, log_patterns_enabled, long_edge_key, spec_label,
 };
+use crate::level::solver::{Deadline, SelectGroup, SelectProblem, canonicalize, select_solver};
 use crate::types::Logger;
-use good_lp::{Expression, ProblemVariables, Solution, SolverModel, constraint, highs, variable};
-
-fn spec_name(c: &Cand) -> &'static str {
-    // patterns.rs と同じ分類（ログ用）
-    use crate::level::patterns::PatternKind;
-    let k = c.spec.kind;
-    if k == PatternKind::Rect2x5 {
-        return match (c.spec.w, c.spec.h) {
-            (5, 2) => "2x5(5x2)",
-            (2, 5) => "2x5(2x5)",
-            _ => "2x5",
-        };
+use std::collections::BTreeMap;
+
+/// 1色分の選択条件（主パターン・端数パターンそれぞれの点数の合計）
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct SelectTarget {
+    pub(crate) color: u8,
+    /// 地を数える単位（主パターンの点数の刻み）
+    pub(crate) unit: usize,
+    pub(crate) main_points: usize,
+    /// 端数（端数パターンの点数の合計）
+    pub(crate) remainder: usize,
+    /// 端数を分けて置いてよいパターンの数の上限
+    pub(crate) pieces: usize,
+    /// 端数を分けたとき、パターンが1つ増えるごとのペナルティ
+    pub(crate) extra_piece_penalty: f64,
+}
+
+impl SelectTarget {
+    /// 端数を複数のパターンに分けてよいか
+    pub(crate) fn splits_remainder(&self) -> bool {
+        self.pieces > 1 && self.remainder > 0
     }
-    if k == PatternKind::Rect3x4 {
-        return match (c.spec.w, c.spec.h) {
-            (4, 3) => "3x4(4x3)",
-            (3, 4) => "3x4(3x4)",
-            _ => "3x4",
-        };
+}
+
+/// パターンの点数（アンカー以外のセル数。主パターンはライブラリの単位の倍数）
+pub(crate) fn pattern_points(c: &Cand) -> f64 {
+    c.spec.points as f64
+}
+
+/// 同時に高々1つしか選べない候補の集合（クリーク）を作る
+///
+/// 同じセルか隣り合う2セルを覆う候補どうしは、重なるか接するので衝突します
+/// （色が異なれば接するだけで衝突）。そこで盤面の辺ごとに、両端のセルを覆う候補を
+/// まとめて1つの制約 Σ x_i <= 1 にします。長辺で並べてよい同色の 2x5 の組は含めないよう、
+/// その辺では候補を分けて複数の集合にします。
+///
+/// `members` の候補だけを対象にし、添字は `members` 内の位置で返します。
+/// 他の集合に含まれる集合は除きます。
+pub(crate) fn conflict_cliques(size: usize, cands: &[Cand], members: &[usize]) -> Vec<Vec<usize>> {
+    let mut cover: Vec<Vec<usize>> = vec![Vec::new(); size * size];
+    for (local, &i) in members.iter().enumerate() {
+        for c in cands[i].mask.cells() {
+            cover[c].push(local);
+        }
     }
-    if k == PatternKind::Rect3x7 {
-        return match (c.spec.w, c.spec.h) {
-            (3, 7) => "3x7(3x7)",
-            (7, 3) => "3x7(7x3)",
-            _ => "3x7",
-        };
+
+    let mut cliques: Vec<Vec<usize>> = Vec::new();
+    let mut push = |mut q: Vec<usize>| {
+        q.sort_unstable();
+        q.dedup();
+        if q.len() >= 2 {
+            cliques.push(q);
+        }
+    };
+    for c in 0..size * size {
+        let (x, y) = (c % size, c / size);
+        if size == 1 {
+            push(cover[c].clone());
+        }
+        let right = (x + 1 < size).then_some((c + 1, true));
+        let below = (y + 1 < size).then_some((c + size, false));
+        for (d, side_by_side) in right.into_iter().chain(below) {
+            // 片側だけを覆う候補のうち、長辺で並べてよい 2x5 はキーごとに分ける
+            let key = |l: usize| long_edge_key(&cands[members[l]], side_by_side);
+            let only_c: Vec<usize> = cover[c]
+                .iter()
+                .copied()
+                .filter(|l| cover[d].binary_search(l).is_err())
+                .collect();
+            let only_d: Vec<usize> = cover[d]
+                .iter()
+                .copied()
+                .filter(|l| cover[c].binary_search(l).is_err())
+                .collect();
+
+            // c を覆う候補と、d だけを覆うキーのない候補（逆も同様）
+            let mut q = cover[c].clone();
+            q.extend(only_d.iter().copied().filter(|&l| key(l).is_none()));
+            push(q);
+            let mut q = cover[d].clone();
+            q.extend(only_c.iter().copied().filter(|&l| key(l).is_none()));
+            push(q);
+
+            // キーの異なる 2x5 どうしは接してはいけない
+            let mut keyed_d: Vec<(usize, LongEdgeKey)> = Vec::new();
+            for &l in &only_d {
+                if let Some(k) = key(l) {
+                    keyed_d.push((l, k));
+                }
+            }
+            if keyed_d.is_empty() {
+                continue;
+            }
+            let mut keyed_c: BTreeMap<LongEdgeKey, Vec<usize>> = BTreeMap::new();
+            for &l in &only_c {
+                if let Some(k) = key(l) {
+                    keyed_c.entry(k).or_default().push(l);
+                }
+            }
+            for (k, mut q) in keyed_c {
+                let before = q.len();
+                q.extend(keyed_d.iter().filter(|&&(_, kd)| kd != k).map(|&(l, _)| l));
+                if q.len() > before {
+                    push(q);
+                }
+            }
+        }
     }
-    if k == PatternKind::Rect1xN {
-        return "1xN";
+    remove_dominated(cliques, members.len())
+}
+
+/// 衝突する候補の組をすべて並べる（`conflict_cliques` と比べるための、要素2つの集合）
+///
+/// 重なる組と、隣り合う2セルの辺をはさんで接する組が衝突します。ただし辺の両側の
+/// `long_edge_key` が等しい組は、その辺では接してよいものとします。
+pub(crate) fn conflict_pairs(size: usize, cands: &[Cand], members: &[usize]) -> Vec<Vec<usize>> {
+    let touches_badly = |a: &Cand, b: &Cand| {
+        a.mask.cells().any(|c| {
+            let (x, y) = (c % size, c / size);
+            let right = (x + 1 < size).then_some((c + 1, true));
+            let left = (x > 0).then(|| (c - 1, true));
+            let below = (y + 1 < size).then_some((c + size, false));
+            let above = (y > 0).then(|| (c - size, false));
+            [right, left, below, above]
+                .into_iter()
+                .flatten()
+                .any(|(d, side_by_side)| {
+                    b.mask.contains(d) && {
+                        let key = long_edge_key(a, side_by_side);
+                        key.is_none() || key != long_edge_key(b, side_by_side)
+                    }
+                })
+        })
+    };
+    let mut pairs: Vec<Vec<usize>> = Vec::new();
+    for (l, &i) in members.iter().enumerate() {
+        for (r, &j) in members.iter().enumerate().skip(l + 1) {
+            let (a, b) = (&cands[i], &cands[j]);
+            if !a.mask_block.intersects(&b.mask) {
+                continue;
+            }
+            if a.mask.intersects(&b.mask) || touches_badly(a, b) {
+                pairs.push(vec![l, r]);
+            }
+        }
     }
-    if k == PatternKind::Rect2xHalf {
-        return "2x(N/2)";
+    pairs
+}
+
+/// 他の集合に含まれる集合（同じ集合の重複を含む）を除く（各集合は昇順）
+fn remove_dominated(mut cliques: Vec<Vec<usize>>, m: usize) -> Vec<Vec<usize>> {
+    // 大きい順に見て、すでに残した集合に含まれるものを捨てる
+    cliques.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    let mut by_member: Vec<Vec<usize>> = vec![Vec::new(); m];
+    let mut kept: Vec<Vec<usize>> = Vec::new();
+    for q in cliques {
+        // q を含む集合は q のどの要素も含むので、含む集合の最も少ない要素から探す
+        let Some(&rarest) = q.iter().min_by_key(|&&i| by_member[i].len()) else {
+            continue;
+        };
+        let dominated = by_member[rarest].iter().any(|&k| is_subset(&q, &kept[k]));
+        if dominated {
+            continue;
+        }
+        for &i in &q {
+            by_member[i].push(kept.len());
+        }
+        kept.push(q);
     }
-    // PatternKind::Rect3x3
-    "3x3"
+    kept
 }
 
-pub(crate) fn solve_select(
+/// 昇順の a が昇順の b に含まれるか
+fn is_subset(a: &[usize], b: &[usize]) -> bool {
+    let mut b = b.iter();
+    a.iter().all(|x| b.any(|y| y == x))
+}
+
+pub(crate) fn log_solution(
+    l: &dyn Logger,
+    backend: &str,
     cands: &[Cand],
-    main_target: usize,
-    remainder_required: usize,
-    logger: Option<&dyn Logger>,
-) -> Result<Vec<usize>, String> {
-    if cands.is_empty() || (main_target == 0 && remainder_required == 0) {
-        return Ok(Vec::new());
+    picked: &[usize],
+    info: &SolveInfo,
+) {
+    let mut cost_sum: u32 = 0;
+    let mut penalty_sum: u32 = 0;
+    let mut pen_perim_sum: u32 = 0;
+    let mut pen_inner_sum: u32 = 0;
+    let mut pen_align_sum: u32 = 0;
+    let mut picked_penalty: usize = 0;
+    for &i in picked {
+        cost_sum = cost_sum.saturating_add(cands[i].cost);
+        penalty_sum = penalty_sum.saturating_add(cands[i].penalty_total);
+        pen_perim_sum = pen_perim_sum.saturating_add(cands[i].penalty_perimeter);
+        pen_inner_sum = pen_inner_sum.saturating_add(cands[i].penalty_internal);
+        pen_align_sum = pen_align_sum.saturating_add(cands[i].penalty_alignment);
+        if cands[i].penalty_total > 0 {
+            picked_penalty += 1;
+        }
     }
+    l.log(&format!(
+        "[{}] status={:?} gap={:?} objective={} picked={} picked_penalty={picked_penalty} cost_sum={cost_sum} penalty_sum={penalty_sum} (perim={pen_perim_sum} inner={pen_inner_sum}) align={pen_align_sum}",
+        backend,
+        info.status,
+        info.gap,
+        info.objective,
+        picked.len()
+    ));
 
-    let m = cands.len();
-    let mut pb = ProblemVariables::new();
-    let xs = (0..m)
-        .map(|_| pb.add(variable().binary()))
-        .collect::<Vec<_>>();
-
-    // 目的: コスト最小化（個数は制約で一致させる）
-    let mut obj: Expression = 0.0.into();
-    for (i, cand) in cands.iter().enumerate() {
-        obj += (cand.cost as f64) * xs[i];
-    }
-    let mut model = pb.minimise(obj).using(highs);
-
-    // 主パターン重み: Σ w_i * x_main_i == main_target
-    let mut sum_main: Expression = 0.0.into();
-    for (i, cand) in cands.iter().enumerate() {
-        if cand.spec.slot == PatternSlot::Main {
-            let w = if cand.spec.kind == crate::level::patterns::PatternKind::Rect3x7 {
-                2.0
-            } else {
-                1.0
-            };
-            sum_main += w * xs[i];
+    if log_patterns_enabled() {
+        for &i in picked {
+            let c = &cands[i];
+            l.log(&format!(
+                "[pick] i={i} color={} spec={} x={} y={} w={} h={} stones={} perimOpp={} innerNoStone={} penPerim={} penInner={} penAlign={} cost={}",
+                c.color,
+                spec_label(&c.spec),
+                c.x,
+                c.y,
+                c.spec.w,
+                c.spec.h,
+                c.stones_in_rect,
+                c.perimeter_opp_cells,
+                c.internal_no_stone_cells,
+                c.penalty_perimeter,
+                c.penalty_internal,
+                c.penalty_alignment,
+                c.cost
+            ));
         }
     }
-    let sum_main_le = sum_main.clone();
-    model = model.with(constraint!(sum_main_le <= main_target as f64));
-    model = model.with(constraint!(sum_main >= main_target as f64));
+}
 
-    // 端数パターン数: Σ x_rem == remainder_required（0 or 1）
-    let mut sum_rem: Expression = 0.0.into();
-    for (i, cand) in cands.iter().enumerate() {
-        if matches!(cand.spec.slot, PatternSlot::Remainder(_)) {
-            sum_rem += xs[i];
-        }
+/// 同じコストの解を1つに決めるための候補の順位
+///
+/// 候補を色・位置（上の行から、同じ行なら左から）・パターンの名前と形の順に並べた順位（1 から）です。
+/// 候補ごとに異なり、ソルバーの種類・版・スレッド数によらず決まります。
+pub(crate) fn canonical_ranks(cands: &[Cand]) -> Vec<u32> {
+    let mut order: Vec<usize> = (0..cands.len()).collect();
+    order.sort_by(|&a, &b| {
+        let key = |c: &Cand| (c.color, c.y, c.x);
+        let (ca, cb) = (&cands[a], &cands[b]);
+        key(ca)
+            .cmp(&key(cb))
+            .then_with(|| ca.spec.name.cmp(&cb.spec.name))
+            .then_with(|| (ca.spec.w, ca.spec.h).cmp(&(cb.spec.w, cb.spec.h)))
+            .then_with(|| ca.spec.cells.cmp(&cb.spec.cells))
+            .then_with(|| ca.spec.anchor_cells.cmp(&cb.spec.anchor_cells))
+    });
+    let mut ranks = vec![0; cands.len()];
+    for (r, &i) in order.iter().enumerate() {
+        ranks[i] = r as u32 + 1;
     }
-    let sum_rem_le = sum_rem.clone();
-    model = model.with(constraint!(sum_rem_le <= remainder_required as f64));
-    model = model.with(constraint!(sum_rem >= remainder_required as f64));
-
-    // 衝突ペア制約 x_i + x_j <= 1
-    let mut conflicts = 0usize;
-    for i in 0..m {
-        for j in (i + 1)..m {
-            if mask_overlaps(&cands[i].mask, &cands[j].mask) {
-                model = model.with(constraint!(xs[i] + xs[j] <= 1));
-                conflicts += 1;
-                continue;
-            }
-            let adjacent = mask_overlaps(&cands[i].mask_block, &cands[j].mask)
-                || mask_overlaps(&cands[j].mask_block, &cands[i].mask);
-            if adjacent && !long_edge_ok_2x5_only(&cands[i], &cands[j]) {
-                model = model.with(constraint!(xs[i] + xs[j] <= 1));
-                conflicts += 1;
+    ranks
+}
+
+/// 選択問題を組み立てる
+///
+/// グループは `targets` の順に、色ごとに主パターン・端数パターンの2つずつ並べます。
+pub(crate) fn build_problem(
+    size: usize,
+    cands: &[Cand],
+    targets: &[SelectTarget],
+    objective: Objective,
+) -> SelectProblem {
+    let m = cands.len();
+    // 目的: コスト最小化（点数は制約で一致させる）
+    let mut costs: Vec<f64> = cands.iter().map(|c| c.objective_cost(objective)).collect();
+
+    let mut groups: Vec<SelectGroup> = Vec::new();
+    for target in targets {
+        // 主パターンの点数: Σ p_i * x_main_i == main_points（地を単位で切り捨てた数）
+        let main = cands
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.color == target.color && c.spec.slot == PatternSlot::Main)
+            .map(|(i, c)| (i, pattern_points(c)))
+            .collect();
+        groups.push(SelectGroup {
+            members: main,
+            target: target.main_points as f64,
+            max_picks: None,
+        });
+
+        // 端数パターンの点数: Σ s_i * x_rem_i == remainder、分けてよい数: Σ x_rem_i <= pieces
+        let rem: Vec<(usize, f64)> = cands
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                c.color == target.color && matches!(c.spec.slot, PatternSlot::Remainder(_))
+            })
+            .map(|(i, c)| (i, pattern_points(c)))
+            .collect();
+        // 大きさ s の端数パターンに penalty * (1 - s/remainder) を加えると、
+        // k 個に分けたときの合計がちょうど penalty * (k - 1) になる
+        if target.splits_remainder() {
+            for &(i, s) in &rem {
+                costs[i] += target.extra_piece_penalty * (1.0 - s / target.remainder as f64);
             }
         }
+        groups.push(SelectGroup {
+            members: rem,
+            target: target.remainder as f64,
+            max_picks: target.splits_remainder().then_some(target.pieces),
+        });
     }
 
-    if let Some(l) = logger {
-        l.log(&format!(
-            "[highs] vars={m} conflicts={conflicts} main_target={main_target} rem_required={remainder_required}"
-        ));
+    // 衝突制約: 同時に選べない候補の集合ごとに Σ x_i <= 1
+    let members: Vec<usize> = (0..m).collect();
+    let cliques = conflict_cliques(size, cands, &members);
+
+    SelectProblem {
+        costs,
+        groups,
+        cliques,
+        cells: cands.iter().map(|c| c.mask.cells().collect()).collect(),
+        cuts: Vec::new(),
+        caps: Vec::new(),
+        objective_cap: None,
     }
+}
 
-    let solution = model.solve().map_err(|e| e.to_string())?;
-    let solve_status = solution.status();
+/// 対象の色と目標をログ用に並べる
+pub(crate) fn describe_targets(targets: &[SelectTarget]) -> String {
+    targets
+        .iter()
+        .map(|t| {
+            format!(
+                "color={} main_points={} remainder={} pieces={}",
+                t.color, t.main_points, t.remainder, t.pieces
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
 
-    let mut picked: Vec<usize> = Vec::new();
-    for (i, x) in xs.iter().enumerate() {
-        let v = solution.value(*x);
-        if v.is_finite() && v > 0.5 {
-            picked.push(i);
-        }
+/// 候補を選ぶ（目的関数の小さい順に最大 k 通り）
+///
+/// 1つ目の解が見つからなければ `Err` を返します。2つ目以降は見つかった分だけ返します。
+/// どの解も `deadline` までの残り時間を制限時間にして解きます。
+pub(crate) fn solve_select(
+    size: usize,
+    cands: &[Cand],
+    targets: &[SelectTarget],
+    options: &LevelOptions,
+    k: usize,
+    deadline: Deadline,
+    logger: Option<&dyn Logger>,
+) -> Result<Vec<(Vec<usize>, SolveInfo)>, String> {
+    let (objective, solver) = (options.objective, &options.solver);
+    if cands.is_empty()
+        || targets
+            .iter()
+            .all(|t| t.main_points == 0 && t.remainder == 0)
+    {
+        return Ok(vec![(Vec::new(), SolveInfo::default())]);
     }
 
+    let mut problem = build_problem(size, cands, targets, objective);
+    let ranks = options.deterministic.then(|| canonical_ranks(cands));
+    let backend = select_solver(solver.backend)?;
+
     if let Some(l) = logger {
-        let mut cost_sum: u32 = 0;
-        let mut penalty_sum: u32 = 0;
-        let mut pen_perim_sum: u32 = 0;
-        let mut pen_inner_sum: u32 = 0;
-        let mut picked_penalty: usize = 0;
-        for &i in &picked {
-            cost_sum = cost_sum.saturating_add(cands[i].cost);
-            penalty_sum = penalty_sum.saturating_add(cands[i].penalty_total);
-            pen_perim_sum = pen_perim_sum.saturating_add(cands[i].penalty_perimeter);
-            pen_inner_sum = pen_inner_sum.saturating_add(cands[i].penalty_internal);
-            if cands[i].penalty_total > 0 {
-                picked_penalty += 1;
-            }
-        }
         l.log(&format!(
-            "[highs] status={solve_status:?} picked={} picked_penalty={picked_penalty} cost_sum={cost_sum} penalty_sum={penalty_sum} (perim={pen_perim_sum} inner={pen_inner_sum})",
-            picked.len()
+            "[{}] vars={} cliques={} nonzeros={} objective={objective:?} {}",
+            backend.name(),
+            cands.len(),
+            problem.cliques.len(),
+            problem.cliques.iter().map(Vec::len).sum::<usize>(),
+            describe_targets(targets)
         ));
+    }
 
-        if log_patterns_enabled() {
-            for &i in &picked {
-                let c = &cands[i];
-                l.log(&format!(
-                    "[pick] i={i} spec={} x={} y={} w={} h={} stones={} perimOpp={} innerNoStone={} penPerim={} penInner={} cost={}",
-                    spec_name(c),
-                    c.x,
-                    c.y,
-                    c.spec.w,
-                    c.spec.h,
-                    c.stones_in_rect,
-                    c.perimeter_opp_cells,
-                    c.internal_no_stone_cells,
-                    c.penalty_perimeter,
-                    c.penalty_internal,
-                    c.cost
-                ));
-            }
+    // 解を1つ見つけるたびに no-good cut で除外して次の解を探す
+    let mut solutions: Vec<(Vec<usize>, SolveInfo)> = Vec::new();
+    for n in 0..k.max(1) {
+        let (mut picked, mut info) = match backend.solve(&problem, &deadline.solver_options(solver))
+        {
+            Ok(v) => v,
+            Err(e) if n == 0 => return Err(e),
+            Err(_) => break,
+        };
+        if let Some(ranks) = &ranks {
+            picked = canonicalize(backend.as_ref(), &problem, ranks, picked, solver, deadline);
+        }
+        info.objective = problem.objective(&picked);
+        if let Some(l) = logger {
+            log_solution(l, backend.name(), cands, &picked, &info);
         }
+        problem.cuts.push(picked.clone());
+        solutions.push((picked, info));
     }
 
-    Ok(picked)
+    Ok(solutions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::constraints::Constraints;
+    use crate::level::options::AlignmentOptions;
+    use crate::level::patterns::{PatternLibrary, generate_candidates};
+    use crate::types::BOARD_SIZE;
+
+    /// 黒の地だけの盤面で、単位 5 の主パターン（1x5・2x5 など）の候補を列挙する
+    fn unit5_cands() -> Vec<Cand> {
+        let n = BOARD_SIZE * BOARD_SIZE;
+        let specs = PatternLibrary::for_unit(5).unwrap().specs().unwrap();
+        generate_candidates(
+            BOARD_SIZE,
+            &vec![0; n],
+            &vec![1; n],
+            1,
+            0,
+            1,
+            &specs,
+            &AlignmentOptions::default(),
+            &Constraints::default(),
+            None,
+        )
+    }
+
+    fn find(cands: &[Cand], x: usize, y: usize, w: usize, h: usize) -> usize {
+        cands
+            .iter()
+            .position(|c| c.x == x && c.y == y && c.spec.w == w && c.spec.h == h)
+            .unwrap()
+    }
+
+    fn conflict(cliques: &[Vec<usize>], a: usize, b: usize) -> bool {
+        cliques.iter().any(|q| q.contains(&a) && q.contains(&b))
+    }
+
+    #[test]
+    fn long_edge_pairs_need_the_same_shape() {
+        let cands = unit5_cands();
+        // 縦長の 1x5 と 2x5 は長辺がそろっていても形が違うので接してはいけない
+        let thin = find(&cands, 0, 0, 1, 5);
+        let wide = find(&cands, 1, 0, 2, 5);
+        let same = find(&cands, 2, 0, 2, 5);
+        let pair = find(&cands, 3, 0, 2, 5);
+        assert!(cands[thin].spec.pair_long_edge && cands[wide].spec.pair_long_edge);
+
+        let members: Vec<usize> = (0..cands.len()).collect();
+        let cliques = conflict_cliques(BOARD_SIZE, &cands, &members);
+        assert!(conflict(&cliques, thin, wide));
+        // 同じ形の 2x5 は長辺で並べてよい
+        assert!(!conflict(&cliques, wide, pair));
+        // 重なる組は衝突する
+        assert!(conflict(&cliques, wide, same));
+    }
 }
@@ -1,9 +1,166 @@
 mod apply;
+mod constraints;
+mod decompose;
+mod diagnose;
 mod ip;
+mod options;
+mod outcome;
 mod patterns;
 mod select;
+mod solver;
 
-use crate::types::{BOARD_SIZE, BoardState, Logger, count_territory};
+pub use diagnose::Diagnostic;
+pub use options::{
+    AlignmentOptions, CellRect, LevelMode, LevelOptions, Objective, SolverBackend, SolverOptions,
+};
+pub use outcome::{
+    ColorReport, LevelOutcome, LevelReport, PickedPattern, RegionOutcome, SolveInfo, SolveStatus,
+};
+pub use patterns::{DEFAULT_UNIT, PatternDef, PatternLibrary, PatternRole};
+pub use solver::{is_infeasible, is_stopped};
+
+use crate::types::{BOARD_SIZE, BoardState, Logger, cell_coords, count_territory, is_dead_stone};
+use constraints::Constraints;
+use select::ColorTarget;
+use solver::Deadline;
+use std::time::{Duration, Instant};
+
+fn color_target(territory: &[u8], color: u8, options: &LevelOptions) -> ColorTarget {
+    let tcount = count_territory(territory, color);
+    let unit = options.unit;
+    // 主パターンの点数は tcount を単位で切り捨てた数、端数は tcount%unit
+    ColorTarget {
+        color,
+        unit,
+        main_points: tcount / unit * unit,
+        remainder: (tcount % unit) as u8,
+        pieces: options.remainder_pieces,
+        conserve_stones: options.conserve_stones,
+    }
+}
+
+/// 整地パターンの候補を列挙し、その数を返す（ベンチマーク用）
+#[doc(hidden)]
+pub fn count_candidates(state: BoardState, color: u8) -> Result<usize, String> {
+    count_candidates_with(state, color, false)
+}
+
+/// `count_candidates` と同じ候補を、外周と内部をセルごとに調べて列挙し、その数を返す（ベンチマークの比較対象）
+#[doc(hidden)]
+pub fn count_candidates_by_scan(state: BoardState, color: u8) -> Result<usize, String> {
+    count_candidates_with(state, color, true)
+}
+
+fn count_candidates_with(state: BoardState, color: u8, scan: bool) -> Result<usize, String> {
+    let state = prepare_board(state)?;
+    let target = color_target(&state.territory, color, &LevelOptions::default());
+    let specs = patterns::resolve_specs(None, DEFAULT_UNIT)?;
+    let size = state.size as usize;
+    let cands = if scan {
+        patterns::scan_candidates(
+            size,
+            &state.stones,
+            &state.territory,
+            color,
+            target.remainder,
+            target.pieces,
+            &specs,
+        )
+    } else {
+        patterns::generate_candidates(
+            size,
+            &state.stones,
+            &state.territory,
+            color,
+            target.remainder,
+            target.pieces,
+            &specs,
+            &AlignmentOptions::default(),
+            &Constraints::default(),
+            None,
+        )
+    };
+    Ok(cands.len())
+}
+
+/// 衝突制約の大きさと、そのモデルを解いた結果（ベンチマーク・テスト用）
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+pub struct ConflictModelStats {
+    /// 衝突制約の数
+    pub constraints: usize,
+    /// 衝突制約の非ゼロ要素の数
+    pub nonzeros: usize,
+    pub objective: f64,
+    pub solve_time: Duration,
+}
+
+/// 1色分の選択問題を、衝突制約をクリークにして（`pairwise` なら衝突する組ごとにして）解く（ベンチマーク・テスト用）
+#[doc(hidden)]
+pub fn solve_conflict_model(
+    state: BoardState,
+    color: u8,
+    backend: SolverBackend,
+    pairwise: bool,
+) -> Result<ConflictModelStats, String> {
+    let state = prepare_board(state)?;
+    let size = state.size as usize;
+    let options = LevelOptions::default();
+    let target = color_target(&state.territory, color, &options);
+    let specs = patterns::resolve_specs(None, DEFAULT_UNIT)?;
+    let cands = patterns::generate_candidates(
+        size,
+        &state.stones,
+        &state.territory,
+        color,
+        target.remainder,
+        target.pieces,
+        &specs,
+        &options.alignment,
+        &Constraints::default(),
+        None,
+    );
+    let targets = [ip::SelectTarget {
+        color,
+        unit: target.unit,
+        main_points: target.main_points,
+        remainder: target.remainder as usize,
+        pieces: target.pieces,
+        extra_piece_penalty: options.extra_piece_penalty,
+    }];
+    let mut problem = ip::build_problem(size, &cands, &targets, options.objective);
+    if pairwise {
+        let members: Vec<usize> = (0..cands.len()).collect();
+        problem.cliques = ip::conflict_pairs(size, &cands, &members);
+    }
+
+    let t = Instant::now();
+    let (picked, _) = solver::select_solver(backend)?.solve(&problem, &options.solver)?;
+    Ok(ConflictModelStats {
+        constraints: problem.cliques.len(),
+        nonzeros: problem.cliques.iter().map(Vec::len).sum(),
+        objective: problem.objective(&picked),
+        solve_time: t.elapsed(),
+    })
+}
+
+/// 盤面を検証し、死石を取り上げる（地として扱う）
+fn prepare_board(mut state: BoardState) -> Result<BoardState, String> {
+    let size = state.size as usize;
+    if size != BOARD_SIZE {
+        return Err("only 19x19 supported".to_string());
+    }
+    let n = size * size;
+    if state.stones.len() != n || state.territory.len() != n {
+        return Err("invalid board arrays length".to_string());
+    }
+    for i in 0..n {
+        if is_dead_stone(&state.stones, &state.territory, i) {
+            state.stones[i] = 0;
+        }
+    }
+    Ok(state)
+}
 
 /// 盤面を整地する
 ///
@@ -15,42 +172,346 @@ use crate::types::{BOARD_SIZE, BoardState, Logger, count_territory};
 ///
 /// # 戻り値
 /// 整地後の盤面状態を返します。エラーが発生した場合は`Err`を返します。
-pub fn level_board(
-    mut state: BoardState,
+pub fn level_board(state: BoardState, logger: Option<&dyn Logger>) -> Result<BoardState, String> {
+    level_board_with_options(state, &LevelOptions::default(), logger).map(|o| o.board)
+}
+
+/// オプションを指定して盤面を整地する
+///
+/// # 引数
+/// - `state`: 整地前の盤面状態
+/// - `options`: 整地オプション
+/// - `logger`: ログ出力用のLogger（オプション）
+///
+/// # 戻り値
+/// 整地後の盤面状態とソルバーの実行結果を返します。エラーが発生した場合は`Err`を返します。
+/// 制限時間（`options.solver.time_limit`）は整地全体にかける時間で、色ごと・領域ごとに解く場合も
+/// その残り時間で解きます。達した場合も、実行可能解が見つかっていればその解で整地します。
+pub fn level_board_with_options(
+    state: BoardState,
+    options: &LevelOptions,
+    logger: Option<&dyn Logger>,
+) -> Result<LevelOutcome, String> {
+    let mut outcomes = level_board_alternatives(state, options, 1, logger)?;
+    Ok(outcomes.remove(0))
+}
+
+/// 整地結果を目的関数の小さい順に最大 `k` 通り列挙する
+///
+/// パターンの選び方を1つ見つけるたびに、その選び方を除外する制約（no-good cut）を加えて解き直します。
+/// 順次整地（`Sequential`）では、黒の上位 `k` 通りのうち最良の黒には白の上位 `k` 通りを、
+/// 残りの黒には最良の白を組み合わせ、両色の合計で並べ替えます。同じ盤面になる結果は1つにまとめます。
+///
+/// # 引数
+/// - `state`: 整地前の盤面状態
+/// - `options`: 整地オプション
+/// - `k`: 求める整地結果の最大数（0 は 1 として扱う）。解く回数は `joint` では最大 `k` 回、
+///   `sequential` では黒の `k` 回と、最良の黒に対する白の `k` 回、残りの黒に対する白の1回ずつで
+///   最大 `3k - 1` 回です。`options.solver.time_limit` はそれらすべてで分け合います
+/// - `logger`: ログ出力用のLogger（オプション）
+///
+/// # 戻り値
+/// 整地結果を目的関数の小さい順に返します。1つも整地できない場合は`Err`を返します。
+pub fn level_board_alternatives(
+    state: BoardState,
+    options: &LevelOptions,
+    k: usize,
     logger: Option<&dyn Logger>,
-) -> Result<BoardState, String> {
+) -> Result<Vec<LevelOutcome>, String> {
+    level_with_deadline(state, options, k, Deadline::new(&options.solver), logger)
+}
+
+/// `deadline` までの残り時間で整地結果を列挙する
+fn level_with_deadline(
+    state: BoardState,
+    options: &LevelOptions,
+    k: usize,
+    deadline: Deadline,
+    logger: Option<&dyn Logger>,
+) -> Result<Vec<LevelOutcome>, String> {
+    let state = prepare_board(state)?;
+    options.validate()?;
     let size = state.size as usize;
-    if size != BOARD_SIZE {
-        return Err("only 19x19 supported".to_string());
+    let constraints = Constraints::new(size, &state.stones, options)?;
+    let k = k.max(1);
+
+    let mut outcomes: Vec<LevelOutcome> = Vec::new();
+    match options.mode {
+        LevelMode::Sequential => {
+            let black = color_target(&state.territory, 1, options);
+            let black_alternatives = select::select_alternatives(
+                size,
+                &state.stones,
+                &state.territory,
+                &[black],
+                options,
+                &constraints,
+                k,
+                deadline,
+                logger,
+            )?;
+            let mut first_error: Option<String> = None;
+            for black_selection in black_alternatives {
+                let mut after_black = state.clone();
+                apply_results(
+                    size,
+                    &mut after_black,
+                    &[black],
+                    black_selection.results,
+                    &constraints,
+                );
+
+                // 白を k 通り列挙するのは、白を整地できた最初の（最良の）黒だけにする
+                let white = color_target(&after_black.territory, 2, options);
+                let white_k = if outcomes.is_empty() { k } else { 1 };
+                let white_alternatives = match select::select_alternatives(
+                    size,
+                    &after_black.stones,
+                    &after_black.territory,
+                    &[white],
+                    options,
+                    &constraints,
+                    white_k,
+                    deadline,
+                    logger,
+                ) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        first_error.get_or_insert(e);
+                        continue;
+                    }
+                };
+                for white_selection in white_alternatives {
+                    let mut board = after_black.clone();
+                    apply_results(
+                        size,
+                        &mut board,
+                        &[white],
+                        white_selection.results,
+                        &constraints,
+                    );
+                    let mut solve = black_selection.solve.clone();
+                    solve.merge(&white_selection.solve);
+                    let mut regions = black_selection.regions.clone();
+                    regions.extend(white_selection.regions);
+                    let mut colors = black_selection.colors.clone();
+                    colors.extend(white_selection.colors);
+                    let mut patterns = black_selection.patterns.clone();
+                    patterns.extend(white_selection.patterns);
+                    let report = LevelReport::new(size, colors, solve.clone(), patterns);
+                    outcomes.push(LevelOutcome {
+                        board,
+                        solve,
+                        regions,
+                        report,
+                    });
+                }
+            }
+            if outcomes.is_empty() {
+                return Err(first_error.unwrap_or_else(|| "no leveling found".to_string()));
+            }
+        }
+        LevelMode::Joint => {
+            // 両色とも整地前の盤面から候補を作り、1つのモデルで選ぶ
+            let targets = [
+                color_target(&state.territory, 1, options),
+                color_target(&state.territory, 2, options),
+            ];
+            let alternatives = select::select_alternatives(
+                size,
+                &state.stones,
+                &state.territory,
+                &targets,
+                options,
+                &constraints,
+                k,
+                deadline,
+                logger,
+            )?;
+            for selection in alternatives {
+                let mut board = state.clone();
+                apply_results(size, &mut board, &targets, selection.results, &constraints);
+                let report = LevelReport::new(
+                    size,
+                    selection.colors,
+                    selection.solve.clone(),
+                    selection.patterns,
+                );
+                outcomes.push(LevelOutcome {
+                    board,
+                    solve: selection.solve,
+                    regions: selection.regions,
+                    report,
+                });
+            }
+        }
     }
-    let n = size * size;
-    if state.stones.len() != n || state.territory.len() != n {
-        return Err("invalid board arrays length".to_string());
+
+    // 目的関数の小さい順（同じ値なら見つかった順）に並べ、同じ盤面を除く
+    outcomes.sort_by(|a, b| a.solve.objective.total_cmp(&b.solve.objective));
+    let mut unique: Vec<LevelOutcome> = Vec::with_capacity(k);
+    for outcome in outcomes {
+        if unique.len() == k {
+            break;
+        }
+        if !unique.iter().any(|u| {
+            u.board.stones == outcome.board.stones && u.board.territory == outcome.board.territory
+        }) {
+            unique.push(outcome);
+        }
     }
+    Ok(unique)
+}
 
-    for color in [1u8, 2u8] {
-        let tcount = count_territory(&state.territory, color);
-        // 主パターン数は floor(tcount/10)、端数は tcount%10
-        let main_target = tcount / 10;
-        let remainder = (tcount % 10) as u8;
-        let (rects, used) = select::select_rects_and_used(
-            size,
-            &state.stones,
-            &state.territory,
-            color,
-            main_target,
-            remainder,
-            logger,
-        )?;
+/// 色ごとの選択結果を盤面に適用する
+fn apply_results(
+    size: usize,
+    state: &mut BoardState,
+    targets: &[ColorTarget],
+    results: Vec<select::SelectResult>,
+    constraints: &Constraints,
+) {
+    for (target, (rects, used)) in targets.iter().zip(results) {
         apply::apply_rects_and_fill(
             size,
             &mut state.stones,
             &mut state.territory,
-            color,
+            target.color,
             &rects,
             &used,
+            constraints,
         );
     }
+}
 
-    Ok(state)
+/// 整地できない理由を調べる
+///
+/// `level_board_with_options` と同じ手順で整地を試し、失敗した色について
+/// 主パターン・端数パターンの候補の有無、地の連結成分ごとの主パターンの置き場所、
+/// パターンどうしの衝突を調べます。動かさない石・パターンを置かない範囲を指定していて、
+/// それらがなければ整地できる場合は `ConstraintsInfeasible` を加えます。
+/// 整地できる場合は空のリストを返します。
+///
+/// 制限時間（`options.solver.time_limit`）は、整地の試行と理由の判定をすべて合わせた時間です。
+/// 時間が足りずに判定できなかった理由は含めません。
+///
+/// # 引数
+/// - `state`: 整地前の盤面状態
+/// - `options`: 整地オプション
+///
+/// # 戻り値
+/// 整地できない理由のリストを返します。盤面や制約が不正な場合は`Err`を返します。
+pub fn diagnose_leveling(
+    state: BoardState,
+    options: &LevelOptions,
+) -> Result<Vec<Diagnostic>, String> {
+    let state = prepare_board(state)?;
+    options.validate()?;
+    let size = state.size as usize;
+    let constraints = Constraints::new(size, &state.stones, options)?;
+    let deadline = Deadline::new(&options.solver);
+    let Some(mut diags) = diagnose_with(state.clone(), options, &constraints, deadline)? else {
+        return Ok(Vec::new());
+    };
+
+    // 制約を外せば整地できるなら、制約が妨げているセルを示す
+    if !constraints.is_empty() {
+        let unconstrained = LevelOptions {
+            locked: Vec::new(),
+            forbidden: Vec::new(),
+            ..options.clone()
+        };
+        if let Ok(outcomes) = level_with_deadline(state.clone(), &unconstrained, 1, deadline, None)
+        {
+            let board = &outcomes[0].board;
+            let cells = (0..size * size).filter(|&i| {
+                (constraints.locked.contains(i) && board.stones[i] != state.stones[i])
+                    || (constraints.forbidden.contains(i) && board.territory[i] != 0)
+            });
+            diags.push(Diagnostic::ConstraintsInfeasible {
+                cells: cell_coords(size, cells),
+            });
+        }
+    }
+    Ok(diags)
+}
+
+/// 制約のもとで整地を試し、失敗した場合はその理由を返す（整地できれば None）
+fn diagnose_with(
+    mut state: BoardState,
+    options: &LevelOptions,
+    constraints: &Constraints,
+    deadline: Deadline,
+) -> Result<Option<Vec<Diagnostic>>, String> {
+    let size = state.size as usize;
+    let specs = patterns::resolve_specs(options.patterns.as_ref(), options.unit)?;
+
+    match options.mode {
+        LevelMode::Sequential => {
+            for color in [1u8, 2u8] {
+                let target = color_target(&state.territory, color, options);
+                match select::select_rects_and_used(
+                    size,
+                    &state.stones,
+                    &state.territory,
+                    target,
+                    options,
+                    constraints,
+                    deadline,
+                    None,
+                ) {
+                    Ok(((rects, used), _)) => apply::apply_rects_and_fill(
+                        size,
+                        &mut state.stones,
+                        &mut state.territory,
+                        color,
+                        &rects,
+                        &used,
+                        constraints,
+                    ),
+                    Err(_) => {
+                        return Ok(Some(diagnose::diagnose_color(
+                            size,
+                            &state.stones,
+                            &state.territory,
+                            target,
+                            &specs,
+                            &options.alignment,
+                            constraints,
+                            deadline,
+                        )));
+                    }
+                }
+            }
+            Ok(None)
+        }
+        LevelMode::Joint => {
+            let targets = [
+                color_target(&state.territory, 1, options),
+                color_target(&state.territory, 2, options),
+            ];
+            match select::select_rects_and_used_joint(
+                size,
+                &state.stones,
+                &state.territory,
+                &targets,
+                options,
+                constraints,
+                deadline,
+                None,
+            ) {
+                Ok(_) => Ok(None),
+                Err(_) => Ok(Some(diagnose::diagnose_joint(
+                    size,
+                    &state.stones,
+                    &state.territory,
+                    &targets,
+                    &specs,
+                    &options.alignment,
+                    constraints,
+                    deadline,
+                ))),
+            }
+        }
+    }
 }
@@ -0,0 +1,188 @@
+use crate::level::patterns::{DEFAULT_UNIT, PatternLibrary, resolve_specs};
+use serde::{Deserialize, Serialize};
+
+/// 整地の進め方
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LevelMode {
+    /// 黒→白の順に1色ずつ整地する（白は黒の整地後の盤面を見る）
+    #[default]
+    Sequential,
+    /// 両色の候補を1つのモデルに入れて同時に選ぶ（色の順序に依存しない）
+    Joint,
+}
+
+/// 整地の目的関数
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    /// 外周・内部ペナルティの合計を最小化する
+    #[default]
+    Penalty,
+    /// 動かす石の数（取り除く石・置く石）の見積もりを最小化する
+    MovedStones,
+    /// 取り除く石・置く石からパターンの外までの距離の合計を最小化する
+    ///
+    /// 実際に石を動かす距離（`compute_stone_moves` の対応）は石の行き先の地によるので、
+    /// この値はその目安です。盤面によっては `Penalty` より長く動かすこともあります。
+    #[serde(alias = "distance")]
+    EdgeDistance,
+}
+
+/// パターン選択に使うソルバー
+///
+/// `highs` / `microlp` はそれぞれ同名の cargo feature を有効にしたときだけ使えます。
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolverBackend {
+    /// HiGHS（MIP）
+    Highs,
+    /// microlp（純Rustの MIP）
+    Microlp,
+    /// 組み込みの分枝限定法（貪欲法で初期解を作る）
+    Builtin,
+    /// 選ぶ個数の制約を主列、占有セルと衝突する候補の集合を副列とする Dancing Links（Algorithm X）の探索
+    ExactCover,
+}
+
+impl SolverBackend {
+    /// このビルドで使えるか
+    pub fn is_available(self) -> bool {
+        match self {
+            SolverBackend::Highs => cfg!(feature = "highs"),
+            SolverBackend::Microlp => cfg!(feature = "microlp"),
+            SolverBackend::Builtin | SolverBackend::ExactCover => true,
+        }
+    }
+}
+
+impl Default for SolverBackend {
+    /// 有効な feature のうち highs → microlp → builtin の順で選ぶ
+    fn default() -> Self {
+        if cfg!(feature = "highs") {
+            SolverBackend::Highs
+        } else if cfg!(feature = "microlp") {
+            SolverBackend::Microlp
+        } else {
+            SolverBackend::Builtin
+        }
+    }
+}
+
+/// ソルバーの選択と打ち切り条件（None はソルバーの既定値）
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SolverOptions {
+    pub backend: SolverBackend,
+    /// 制限時間（秒）。達した場合はそれまでの最良解を返す
+    ///
+    /// microlp は途中で止められないので、指定するとエラーになります。
+    pub time_limit: Option<f64>,
+    /// 許容する相対ギャップ（例: 0.01 で 1%）
+    pub mip_rel_gap: Option<f64>,
+    /// ソルバーのスレッド数
+    pub threads: Option<u32>,
+}
+
+/// 主パターンの並べ方の好み（候補のコストに加えるペナルティ。0 で無効）
+///
+/// 盤端や壁に沿って、互いに行・列をそろえて並んだ塊は一目で数えられます。
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlignmentOptions {
+    /// 盤端に接する辺がない主パターンに加えるペナルティ
+    pub prefer_edge: u32,
+    /// 盤端にも壁（同色の石）にも接する辺がない主パターンに加えるペナルティ
+    pub prefer_wall: u32,
+    /// 最も近い盤端からの距離が、塊の幅（高さ）の倍数でない向き1つごとに加えるペナルティ
+    /// （盤端から同じ大きさの塊を敷き詰めた格子に乗ると、隣の塊と行・列がそろう）
+    pub prefer_aligned_rows: u32,
+}
+
+/// 盤面上の長方形（左上のセル (x, y) と幅・高さ）
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellRect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// 整地オプション
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelOptions {
+    pub mode: LevelMode,
+    pub objective: Objective,
+    pub solver: SolverOptions,
+    /// 互いに影響しない領域に分けて解く（既定は true）
+    pub decompose: bool,
+    /// 地を数える単位（既定は 10。5 や 20 も使える）。主パターンの点数の合計は地をこの単位で
+    /// 切り捨てた数、端数は地をこの単位で割った余りになる
+    pub unit: usize,
+    /// 整地パターンのライブラリ（None は単位に応じた既定のライブラリ）
+    pub patterns: Option<PatternLibrary>,
+    /// 端数を分けて置いてよいパターンの数の上限（既定は 1）
+    ///
+    /// 2 以上なら、大きさの合計が端数になる端数パターンを上限まで組み合わせて使えます。
+    /// ちょうどこの数に分けるとは限らず、目的関数が小さくなる数（この値以下）を選びます。
+    pub remainder_pieces: usize,
+    /// 端数を分けたとき、パターンが1つ増えるごとに目的関数に加えるペナルティ（既定は 10）
+    pub extra_piece_penalty: f64,
+    /// 主パターンの並べ方の好み（既定はすべて 0）
+    pub alignment: AlignmentOptions,
+    /// 動かさない石のセル (x, y)。パターンはこの石を取り除かない（アンカーとして残すのはよい）
+    pub locked: Vec<(u32, u32)>,
+    /// パターンを置かない長方形（中の地は石で埋める）
+    pub forbidden: Vec<CellRect>,
+    /// 石を置き直すだけで整地する（既定は false）
+    ///
+    /// 整地前後で色ごとの石の数が変わらないことを確かめ、変わるなら整地せずにエラーを返します。
+    /// 石の数が変わらなければ、必ず石の移動の組に分けられます。
+    pub conserve_stones: bool,
+    /// コストが同じ解のうち、盤面の上・左に寄せた選び方を選ぶ（既定は true）
+    ///
+    /// 解いた後、コストをその値に固定し、候補の位置の順位の合計を最小にするよう解き直すので、
+    /// ソルバーの種類・版・スレッド数によらず同じ入力から同じ盤面になります。false にすると
+    /// 解き直さないぶん速くなりますが、どの解になるかはソルバーに任せます。
+    pub deterministic: bool,
+}
+
+impl Default for LevelOptions {
+    fn default() -> Self {
+        LevelOptions {
+            mode: LevelMode::default(),
+            objective: Objective::default(),
+            solver: SolverOptions::default(),
+            decompose: true,
+            unit: DEFAULT_UNIT,
+            patterns: None,
+            remainder_pieces: 1,
+            extra_piece_penalty: 10.0,
+            alignment: AlignmentOptions::default(),
+            locked: Vec::new(),
+            forbidden: Vec::new(),
+            conserve_stones: false,
+            deterministic: true,
+        }
+    }
+}
+
+impl LevelOptions {
+    /// 単位・パターンのライブラリ・端数の分け方を検証する（ライブラリの単位は `unit` と一致すること）
+    pub fn validate(&self) -> Result<(), String> {
+        if self.remainder_pieces == 0 {
+            return Err("remainder_pieces must be at least 1".to_string());
+        }
+        if !(self.extra_piece_penalty.is_finite() && self.extra_piece_penalty >= 0.0) {
+            return Err("extra_piece_penalty must be a non-negative number".to_string());
+        }
+        if self.solver.backend == SolverBackend::Microlp && self.solver.time_limit.is_some() {
+            return Err(
+                "the microlp backend cannot stop at time_limit; use builtin, exact_cover or highs"
+                    .to_string(),
+            );
+        }
+        resolve_specs(self.patterns.as_ref(), self.unit).map(|_| ())
+    }
+}
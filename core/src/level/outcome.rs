@@ -0,0 +1,150 @@
+use crate::level::patterns::PatternRole;
+use crate::types::BoardState;
+use serde::{Deserialize, Serialize};
+
+/// ソルバーの終了状態
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolveStatus {
+    /// 最適解が見つかった
+    #[default]
+    Optimal,
+    /// 指定した相対ギャップ以内の解で打ち切った
+    GapLimit,
+    /// 制限時間に達したため、それまでの最良解を返した
+    TimeLimit,
+}
+
+/// ソルバーの実行結果（複数回解いた場合は最も悪い状態と最大のギャップ）
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SolveInfo {
+    pub status: SolveStatus,
+    /// 選んだパターンの目的関数値の合計
+    pub objective: f64,
+    /// 最良解と下界との相対ギャップ（最適性が証明できなかった場合のみ）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap: Option<f64>,
+}
+
+impl SolveInfo {
+    pub(crate) fn merge(&mut self, other: &SolveInfo) {
+        self.status = self.status.max(other.status);
+        self.objective += other.objective;
+        self.gap = match (self.gap, other.gap) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+/// 独立に解いた領域ごとの結果
+///
+/// 領域は、パターンの候補が重なる・接する・同じ地の連結成分にかかることでつながった地の連結成分の集まりです。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RegionOutcome {
+    pub color: u8,
+    /// 領域に含まれる地のセル（座標は (x, y)）
+    pub cells: Vec<(u32, u32)>,
+    /// 割り当てた主パターンの点数の合計
+    pub main_points: usize,
+    /// 端数パターンを割り当てたか
+    pub remainder: bool,
+    /// 領域のソルバーの実行結果（両色を含む領域では両色で同じ値）
+    #[serde(flatten)]
+    pub solve: SolveInfo,
+}
+
+/// 色ごとの整地目標と候補の数
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColorReport {
+    pub color: u8,
+    /// 主パターンの点数の合計の目標
+    pub main_points: usize,
+    /// 端数
+    pub remainder: u8,
+    /// 主パターンの候補の数
+    pub main_candidates: usize,
+    /// 端数パターンの候補の数
+    pub remainder_candidates: usize,
+}
+
+/// 選んだパターン（座標は (x, y)）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PickedPattern {
+    /// パターンの番号（`LevelReport::cells` の値）
+    pub id: usize,
+    pub color: u8,
+    /// ライブラリでの名前（向きによらず同じ）
+    pub name: String,
+    pub role: PatternRole,
+    /// 点数（10目ずつ数えるなら 10・20・30 など）
+    pub points: u32,
+    /// 外接する長方形の左上と幅・高さ
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+    /// パターンのセル
+    pub cells: Vec<(u32, u32)>,
+    /// アンカーセル（石を残す・置くセル）
+    pub anchors: Vec<(u32, u32)>,
+    /// 候補のコスト（外周・内部ペナルティと並べ方のペナルティの合計）
+    pub cost: u32,
+    /// 外周ペナルティ（外側で接する相手色のセル）
+    pub penalty_perimeter: u32,
+    /// 内部ペナルティ（取り除く石と、石のないアンカーセル）
+    pub penalty_internal: u32,
+    /// 並べ方の好みによるペナルティ
+    pub penalty_alignment: u32,
+}
+
+/// 整地の内訳
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LevelReport {
+    /// 色ごとの目標と候補の数（順次整地では白は黒の整地後の盤面から数える）
+    pub colors: Vec<ColorReport>,
+    /// ソルバーの実行結果
+    pub solve: SolveInfo,
+    /// 選んだパターン（色・位置の順）
+    pub patterns: Vec<PickedPattern>,
+    /// セルごとのパターンの番号（y * size + x の順。パターンのないセルは None）
+    pub cells: Vec<Option<usize>>,
+}
+
+impl LevelReport {
+    /// 色ごとの内訳と選んだパターンからまとめる（パターンの番号は並べた順に振り直す）
+    pub(crate) fn new(
+        size: usize,
+        colors: Vec<ColorReport>,
+        solve: SolveInfo,
+        mut patterns: Vec<PickedPattern>,
+    ) -> LevelReport {
+        patterns.sort_by_key(|p| (p.color, p.y, p.x));
+        let mut cells = vec![None; size * size];
+        for (id, p) in patterns.iter_mut().enumerate() {
+            p.id = id;
+            for &(x, y) in &p.cells {
+                cells[y as usize * size + x as usize] = Some(id);
+            }
+        }
+        LevelReport {
+            colors,
+            solve,
+            patterns,
+            cells,
+        }
+    }
+}
+
+/// 整地結果
+#[derive(Clone, Debug)]
+pub struct LevelOutcome {
+    /// 整地後の盤面状態
+    pub board: BoardState,
+    /// ソルバーの実行結果
+    pub solve: SolveInfo,
+    /// 領域ごとの結果（領域に分けて解いた場合のみ）
+    pub regions: Vec<RegionOutcome>,
+    /// 色ごとの候補の数と、選んだパターン・セルごとのパターンの番号
+    pub report: LevelReport,
+}
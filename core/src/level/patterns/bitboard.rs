@@ -0,0 +1,155 @@
+use crate::types::BOARD_SIZE;
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+/// 盤面のセル数を覆うワード数
+pub(crate) const BOARD_WORDS: usize = (BOARD_SIZE * BOARD_SIZE).div_ceil(64);
+
+/// 盤面のセルの集合（セル i はワード i/64 のビット i%64）
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Bitboard([u64; BOARD_WORDS]);
+
+impl Bitboard {
+    pub(crate) fn set(&mut self, i: usize) {
+        self.0[i / 64] |= 1u64 << (i % 64);
+    }
+
+    pub(crate) fn contains(&self, i: usize) -> bool {
+        self.0[i / 64] & (1u64 << (i % 64)) != 0
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.iter().all(|&w| w == 0)
+    }
+
+    /// 共通のセルがあるか（ワード単位で比べる）
+    pub(crate) fn intersects(&self, other: &Bitboard) -> bool {
+        self.0.iter().zip(&other.0).any(|(a, b)| a & b != 0)
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.0.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// 含まれるセルの添字（昇順）
+    pub(crate) fn cells(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(w, &bits)| {
+            let mut bits = bits;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let b = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(w * 64 + b)
+            })
+        })
+    }
+
+    /// セルの添字を `n` だけ大きい方へずらす（盤面の外に出たセルは捨てる）
+    pub(crate) fn shift_up(&self, n: usize) -> Bitboard {
+        let (words, bits) = (n / 64, n % 64);
+        let mut out = [0u64; BOARD_WORDS];
+        for (i, o) in out.iter_mut().enumerate().skip(words) {
+            let src = i - words;
+            *o = self.0[src] << bits;
+            if bits > 0 && src > 0 {
+                *o |= self.0[src - 1] >> (64 - bits);
+            }
+        }
+        Bitboard(out)
+    }
+
+    /// セルの添字を `n` だけ小さい方へずらす（0 より前に出たセルは捨てる）
+    pub(crate) fn shift_down(&self, n: usize) -> Bitboard {
+        let (words, bits) = (n / 64, n % 64);
+        let mut out = [0u64; BOARD_WORDS];
+        for (i, o) in out
+            .iter_mut()
+            .take(BOARD_WORDS.saturating_sub(words))
+            .enumerate()
+        {
+            let src = i + words;
+            *o = self.0[src] >> bits;
+            if bits > 0 && src + 1 < BOARD_WORDS {
+                *o |= self.0[src + 1] << (64 - bits);
+            }
+        }
+        Bitboard(out)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+
+    fn bitor(mut self, rhs: Bitboard) -> Bitboard {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Bitboard) {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a |= b;
+        }
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+
+    fn bitand(mut self, rhs: Bitboard) -> Bitboard {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a &= b;
+        }
+        self
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(mut self) -> Bitboard {
+        for a in &mut self.0 {
+            *a = !*a;
+        }
+        self
+    }
+}
+
+/// 盤面の大きさごとの列・盤面のマスク（4近傍への膨張用）
+pub(crate) struct BoardMasks {
+    size: usize,
+    board: Bitboard,
+    first_col: Bitboard,
+    last_col: Bitboard,
+}
+
+impl BoardMasks {
+    pub(crate) fn new(size: usize) -> BoardMasks {
+        let mut masks = BoardMasks {
+            size,
+            board: Bitboard::default(),
+            first_col: Bitboard::default(),
+            last_col: Bitboard::default(),
+        };
+        for i in 0..size * size {
+            masks.board.set(i);
+            if i % size == 0 {
+                masks.first_col.set(i);
+            }
+            if i % size == size - 1 {
+                masks.last_col.set(i);
+            }
+        }
+        masks
+    }
+
+    /// セルの集合に、盤上の4近傍を加える
+    pub(crate) fn dilate(&self, m: &Bitboard) -> Bitboard {
+        let left = m.shift_down(1) & !self.last_col;
+        let right = m.shift_up(1) & !self.first_col;
+        let vertical = m.shift_down(self.size) | m.shift_up(self.size);
+        (*m | left | right | vertical) & self.board
+    }
+}
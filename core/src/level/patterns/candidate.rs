@@ -1,229 +1,355 @@
-use super::specs::{PATTERN_SPECS, REMAINDER_SPECS};
-use super::types::{Cand, PatternKind, PatternSlot, PatternSpec};
-use super::utils::{cell_in_pattern, log_patterns_enabled, mask_set, slot_name_pub, spec_name_pub};
+use super::bitboard::{BOARD_WORDS, Bitboard, BoardMasks};
+use super::prefix::PrefixSum;
+use super::types::{Cand, PatternSlot, PatternSpec};
+use super::utils::{cell_in_pattern, log_patterns_enabled, slot_name, spec_label};
+use crate::level::constraints::Constraints;
+use crate::level::options::AlignmentOptions;
 use crate::types::{Logger, NEIGH4, idx2};
+use std::sync::Arc;
 
-// 外周判定:
-// - 同色（石/地）または境界: OK
-// - 相手色（石/地）: OK だが 1セルにつきペナルティ+10
-// - それ以外（空など）: NG
-fn perimeter_class(
-    size: usize,
-    stones: &[u8],
-    territory: &[u8],
-    x: isize,
-    y: isize,
-    color: u8,
-) -> u8 {
-    if x < 0 || y < 0 || x >= size as isize || y >= size as isize {
-        return 1; // boundary
-    }
-    let i = idx2(size, x as usize, y as usize);
-    if stones[i] == color || territory[i] == color {
-        return 1; // same color
-    }
-    let opp = if color == 1 { 2 } else { 1 };
-    if stones[i] == opp || territory[i] == opp {
-        return 2; // opponent
+/// 候補の判定に使う盤面の累積和
+///
+/// 外周判定:
+/// - 同色（石/地）または境界: OK
+/// - 相手色（石/地）: OK だが 1セルにつきペナルティ+10
+/// - それ以外（空など）: NG
+struct BoardSums {
+    /// 石のあるセル
+    stones: PrefixSum,
+    /// 同色の石か同色の地のセル
+    own: PrefixSum,
+    /// 同色の地のセル
+    territory: PrefixSum,
+    /// 同色でない、相手色の石か相手色の地のセル
+    opp: PrefixSum,
+    /// 同色でも相手色でもないセル
+    other: PrefixSum,
+}
+
+impl BoardSums {
+    fn new(size: usize, stones: &[u8], territory: &[u8], color: u8) -> BoardSums {
+        let opp_color = if color == 1 { 2 } else { 1 };
+        let own = |i: usize| stones[i] == color || territory[i] == color;
+        let opp = |i: usize| !own(i) && (stones[i] == opp_color || territory[i] == opp_color);
+        BoardSums {
+            stones: PrefixSum::new(size, |i| stones[i] != 0),
+            own: PrefixSum::new(size, own),
+            territory: PrefixSum::new(size, |i| territory[i] == color),
+            opp: PrefixSum::new(size, opp),
+            other: PrefixSum::new(size, |i| !own(i) && !opp(i)),
+        }
     }
-    0 // other (e.g. empty)
 }
 
-// 外周チェック（4方向）: 上/下/左/右が囲まれているか確認
-// 戻り値: (ok, perimeter_opp_cells, penalty_perimeter)
-#[allow(clippy::too_many_arguments)]
-fn check_perimeter_4_sides(
-    size: usize,
-    stones: &[u8],
-    territory: &[u8],
-    x: usize,
-    y: usize,
+/// セルの集合を分けた長方形（ローカル座標。外周では -1 や w, h にもなる）
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Strip {
+    x: isize,
+    y: isize,
     w: usize,
     h: usize,
-    color: u8,
-) -> (bool, u32, u32) {
-    let mut perimeter_opp_cells = 0u32;
-    let mut penalty_perimeter = 0u32;
-
-    // 上（幅 w）
-    for dx in 0..w {
-        match perimeter_class(
-            size,
-            stones,
-            territory,
-            (x + dx) as isize,
-            (y as isize) - 1,
-            color,
-        ) {
-            1 => {}
-            2 => {
-                perimeter_opp_cells += 1;
-                penalty_perimeter += 10;
-            }
-            _ => return (false, 0, 0),
+}
+
+/// セルの集合を、行ごとの連続区間に分け、上下に同じ区間が続くものを1つの長方形にまとめる
+fn strips(mut cells: Vec<(isize, isize)>) -> Vec<Strip> {
+    cells.sort_unstable_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+    let mut out: Vec<Strip> = Vec::new();
+    let mut i = 0;
+    while i < cells.len() {
+        let (x, y) = cells[i];
+        let mut w = 1;
+        while i + w < cells.len() && cells[i + w] == (x + w as isize, y) {
+            w += 1;
+        }
+        i += w;
+        match out
+            .iter_mut()
+            .find(|s| s.x == x && s.w == w && s.y + s.h as isize == y)
+        {
+            Some(s) => s.h += 1,
+            None => out.push(Strip { x, y, w, h: 1 }),
         }
     }
+    out
+}
 
-    // 下（幅 w）
-    for dx in 0..w {
-        match perimeter_class(
-            size,
-            stones,
-            territory,
-            (x + dx) as isize,
-            (y + h) as isize,
-            color,
-        ) {
-            1 => {}
-            2 => {
-                perimeter_opp_cells += 1;
-                penalty_perimeter += 10;
+/// 位置によらないパターンの形
+struct Shape {
+    spec: Arc<PatternSpec>,
+    /// 左上を (0, 0) に置いたときのセル
+    mask: Bitboard,
+    /// パターンのセルを分けた長方形
+    body: Vec<Strip>,
+    /// パターンの外で4近傍にパターンのセルがあるセル（外周）を分けた長方形
+    border: Vec<Strip>,
+    /// パターンのセルと外周のセル（ローカル座標。`Lookup::Scan` で1つずつ調べる）
+    body_cells: Vec<(isize, isize)>,
+    border_cells: Vec<(isize, isize)>,
+    /// パターンのセル数
+    cells: u32,
+    /// パターン内のアンカーセル（ローカル座標）
+    anchors: Vec<(usize, usize)>,
+    /// `NEIGH4` の向きごとに、その向きでパターンのセルに接する外側のセル（ローカル座標）
+    sides: [Vec<(isize, isize)>; 4],
+}
+
+impl Shape {
+    fn new(size: usize, spec: &Arc<PatternSpec>) -> Shape {
+        let mut mask = Bitboard::default();
+        let mut cells: Vec<(isize, isize)> = Vec::new();
+        for dy in 0..spec.h {
+            for dx in 0..spec.w {
+                if cell_in_pattern(dx, dy, spec) {
+                    mask.set(idx2(size, dx, dy));
+                    cells.push((dx as isize, dy as isize));
+                }
             }
-            _ => return (false, 0, 0),
+        }
+        let inside = |x: isize, y: isize| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < spec.w
+                && (y as usize) < spec.h
+                && cell_in_pattern(x as usize, y as usize, spec)
+        };
+        let mut border: Vec<(isize, isize)> = cells
+            .iter()
+            .flat_map(|&(x, y)| NEIGH4.iter().map(move |&(nx, ny)| (x + nx, y + ny)))
+            .filter(|&(x, y)| !inside(x, y))
+            .collect();
+        border.sort_unstable();
+        border.dedup();
+        let sides = NEIGH4.map(|(nx, ny)| {
+            cells
+                .iter()
+                .map(|&(x, y)| (x + nx, y + ny))
+                .filter(|&(x, y)| !inside(x, y))
+                .collect()
+        });
+        Shape {
+            spec: spec.clone(),
+            mask,
+            sides,
+            cells: cells.len() as u32,
+            body: strips(cells.clone()),
+            border: strips(border.clone()),
+            body_cells: cells,
+            border_cells: border,
+            anchors: spec
+                .anchor_cells
+                .iter()
+                .copied()
+                .filter(|&(dx, dy)| cell_in_pattern(dx, dy, spec))
+                .collect(),
         }
     }
 
-    // 左（高さ h）
-    for dy in 0..h {
-        match perimeter_class(
-            size,
-            stones,
-            territory,
-            (x as isize) - 1,
-            (y + dy) as isize,
-            color,
-        ) {
-            1 => {}
-            2 => {
-                perimeter_opp_cells += 1;
-                penalty_perimeter += 10;
-            }
-            _ => return (false, 0, 0),
-        }
+    /// 左上 (x, y) に置いたパターン内で条件を満たすセルの数
+    fn count(&self, sum: &PrefixSum, x: usize, y: usize) -> u32 {
+        self.body
+            .iter()
+            .map(|s| sum.count(x + s.x as usize, y + s.y as usize, s.w, s.h))
+            .sum()
     }
+}
 
-    // 右（高さ h）
-    for dy in 0..h {
-        match perimeter_class(
-            size,
-            stones,
-            territory,
-            (x + w) as isize,
-            (y + dy) as isize,
-            color,
-        ) {
-            1 => {}
-            2 => {
-                perimeter_opp_cells += 1;
-                penalty_perimeter += 10;
-            }
-            _ => return (false, 0, 0),
+// 外周チェック: パターンの形の外側で4近傍に接するセルを、長方形ごとに累積和で数える
+// 戻り値: 外周がNGなら None、OKなら Some((perimeter_opp_cells, penalty_perimeter))
+fn check_perimeter(
+    size: usize,
+    sums: &BoardSums,
+    x: usize,
+    y: usize,
+    shape: &Shape,
+) -> Option<(u32, u32)> {
+    let mut perimeter_opp_cells = 0u32;
+    for s in &shape.border {
+        // 盤外（境界）のセルは数えない
+        let (sx, sy) = (x as isize + s.x, y as isize + s.y);
+        let x0 = sx.max(0) as usize;
+        let y0 = sy.max(0) as usize;
+        let x1 = (sx + s.w as isize).clamp(0, size as isize) as usize;
+        let y1 = (sy + s.h as isize).clamp(0, size as isize) as usize;
+        if x0 >= x1 || y0 >= y1 {
+            continue;
         }
+        if sums.other.count(x0, y0, x1 - x0, y1 - y0) > 0 {
+            return None;
+        }
+        perimeter_opp_cells += sums.opp.count(x0, y0, x1 - x0, y1 - y0);
     }
+    Some((perimeter_opp_cells, perimeter_opp_cells * 10))
+}
 
-    (true, perimeter_opp_cells, penalty_perimeter)
+/// 候補の判定の仕方
+#[derive(Copy, Clone)]
+enum Lookup<'a> {
+    /// 長方形ごとに累積和で数える
+    PrefixSums,
+    /// 外周と内部のセルを1つずつ調べる（累積和を使う前の方法。ベンチマークで比べる）
+    Scan { territory: &'a [u8] },
 }
 
-// 内部制約チェック: 内部は「同色の石 or 同色の地」のみで構成されていること
-fn check_internal_constraint(
+// `check_perimeter` と同じ判定を、外周のセルを1つずつ調べて行う
+fn scan_perimeter(
     size: usize,
     stones: &[u8],
     territory: &[u8],
+    color: u8,
     x: usize,
     y: usize,
-    spec: &PatternSpec,
-    color: u8,
-) -> bool {
-    for dy in 0..spec.h {
-        for dx in 0..spec.w {
-            if !cell_in_pattern(dx, dy, spec) {
-                continue;
-            }
-            let i = idx2(size, x + dx, y + dy);
-            if !(stones[i] == color || territory[i] == color) {
-                return false;
-            }
+    shape: &Shape,
+) -> Option<(u32, u32)> {
+    let opp_color = if color == 1 { 2 } else { 1 };
+    let mut perimeter_opp_cells = 0u32;
+    for &(lx, ly) in &shape.border_cells {
+        let (bx, by) = (x as isize + lx, y as isize + ly);
+        if bx < 0 || by < 0 || bx >= size as isize || by >= size as isize {
+            continue;
         }
+        let i = idx2(size, bx as usize, by as usize);
+        if stones[i] == color || territory[i] == color {
+            continue;
+        }
+        if stones[i] != opp_color && territory[i] != opp_color {
+            return None;
+        }
+        perimeter_opp_cells += 1;
     }
-    true
+    Some((perimeter_opp_cells, perimeter_opp_cells * 10))
+}
+
+// パターン内のセルが、すべて同色の石か同色の地か（セルを1つずつ調べる）
+fn scan_own(
+    size: usize,
+    stones: &[u8],
+    territory: &[u8],
+    color: u8,
+    x: usize,
+    y: usize,
+    shape: &Shape,
+) -> bool {
+    shape.body_cells.iter().all(|&(lx, ly)| {
+        let i = idx2(size, x + lx as usize, y + ly as usize);
+        stones[i] == color || territory[i] == color
+    })
 }
 
-// 内部ペナルティ計算とmask生成
-// 戻り値: (stones_in_rect, penalty_internal, internal_no_stone_cells, mask, mask_block)
-fn calculate_internal_penalty_and_masks(
+// 内部ペナルティ計算
+// アンカーセルは石がなければ +1、通常セルは石があれば +1
+// 戻り値: (stones_in_rect, penalty_internal, internal_no_stone_cells)
+fn calculate_internal_penalty(
     size: usize,
     stones: &[u8],
+    sums: &BoardSums,
     x: usize,
     y: usize,
+    shape: &Shape,
+) -> (u32, u32, u32) {
+    let stones_in_rect = shape.count(&sums.stones, x, y);
+    let anchor_stones = shape
+        .anchors
+        .iter()
+        .filter(|&&(dx, dy)| stones[idx2(size, x + dx, y + dy)] != 0)
+        .count() as u32;
+    let internal_no_stone_cells = shape.anchors.len() as u32 - anchor_stones;
+    let penalty_internal = (stones_in_rect - anchor_stones) + internal_no_stone_cells;
+    (stones_in_rect, penalty_internal, internal_no_stone_cells)
+}
+
+// パターン内のセルから、上下左右に直進してパターンの外（盤上）に出るまでの最短距離
+fn distance_to_outside(
+    size: usize,
+    x: usize,
+    y: usize,
+    dx: usize,
+    dy: usize,
     spec: &PatternSpec,
-    words: usize,
-) -> (u32, u32, u32, Vec<u64>, Vec<u64>) {
-    let mut mask = vec![0u64; words];
-    let mut mask_block = vec![0u64; words];
-    let mut stones_in_rect = 0u32;
-    let mut penalty_internal = 0u32;
-    let mut internal_no_stone_cells = 0u32;
+) -> u32 {
+    let mut best = u32::MAX;
+    for (sx, sy) in NEIGH4 {
+        let mut k = 1isize;
+        loop {
+            let lx = dx as isize + sx * k;
+            let ly = dy as isize + sy * k;
+            let inside = lx >= 0
+                && ly >= 0
+                && (lx as usize) < spec.w
+                && (ly as usize) < spec.h
+                && cell_in_pattern(lx as usize, ly as usize, spec);
+            if !inside {
+                let bx = x as isize + lx;
+                let by = y as isize + ly;
+                if bx >= 0 && by >= 0 && bx < size as isize && by < size as isize {
+                    best = best.min(k as u32);
+                }
+                break;
+            }
+            k += 1;
+        }
+    }
+    best
+}
 
+// 石の移動量の見積もり: 取り除く石・置く石それぞれの、パターンの外までの距離の合計
+fn estimate_movement(size: usize, stones: &[u8], x: usize, y: usize, spec: &PatternSpec) -> u32 {
+    let mut move_distance = 0u32;
     for dy in 0..spec.h {
         for dx in 0..spec.w {
             if !cell_in_pattern(dx, dy, spec) {
                 continue;
             }
             let i = idx2(size, x + dx, y + dy);
-            mask_set(&mut mask, i);
-            mask_set(&mut mask_block, i);
             let is_anchor = spec
                 .anchor_cells
                 .iter()
                 .any(|&(cx, cy)| cx == dx && cy == dy);
-            let has_stone = stones[i] != 0;
-            if has_stone {
-                stones_in_rect += 1;
-            }
-            if is_anchor {
-                // anchorセル: 石がなければペナルティ+1
-                if !has_stone {
-                    internal_no_stone_cells += 1;
-                    penalty_internal = penalty_internal.saturating_add(1);
-                }
-            } else {
-                // 通常セル: 石があればペナルティ+1
-                if has_stone {
-                    penalty_internal = penalty_internal.saturating_add(1);
-                }
+            // 石を取り除くセル・石を置くセルは、パターンの外との間で石が動く
+            if is_anchor != (stones[i] != 0) {
+                move_distance =
+                    move_distance.saturating_add(distance_to_outside(size, x, y, dx, dy, spec));
             }
         }
     }
-
-    (
-        stones_in_rect,
-        penalty_internal,
-        internal_no_stone_cells,
-        mask,
-        mask_block,
-    )
+    move_distance
 }
 
-// mask_blockに4近傍を追加（隣接禁止用）
-fn build_mask_block(size: usize, x: usize, y: usize, spec: &PatternSpec, mask_block: &mut [u64]) {
-    for dy in 0..spec.h {
-        for dx in 0..spec.w {
-            if !cell_in_pattern(dx, dy, spec) {
-                continue;
-            }
-            let cx = (x + dx) as isize;
-            let cy = (y + dy) as isize;
-            for (nx, ny) in NEIGH4 {
-                let px = cx + nx;
-                let py = cy + ny;
-                if px < 0 || py < 0 || px >= size as isize || py >= size as isize {
-                    continue;
-                }
-                let pi = idx2(size, px as usize, py as usize);
-                mask_set(mask_block, pi);
-            }
-        }
+// 並べ方の好みによるペナルティ（主パターンのみ）
+// 盤端・壁に接するのは、ある向きでパターンに接する外側のセルがすべて盤外（か同色の石）のとき
+fn alignment_penalty(
+    size: usize,
+    stones: &[u8],
+    x: usize,
+    y: usize,
+    shape: &Shape,
+    color: u8,
+    alignment: &AlignmentOptions,
+) -> u32 {
+    if shape.spec.slot != PatternSlot::Main {
+        return 0;
+    }
+    let flush = |wall: bool| {
+        shape.sides.iter().any(|side| {
+            side.iter().all(|&(lx, ly)| {
+                let (bx, by) = (x as isize + lx, y as isize + ly);
+                let off_board = bx < 0 || by < 0 || bx >= size as isize || by >= size as isize;
+                off_board || (wall && stones[idx2(size, bx as usize, by as usize)] == color)
+            })
+        })
+    };
+    let misaligned = |pos: usize, len: usize| !pos.min(size - pos - len).is_multiple_of(len);
+
+    let mut penalty = 0u32;
+    if alignment.prefer_edge > 0 && !flush(false) {
+        penalty = penalty.saturating_add(alignment.prefer_edge);
+    }
+    if alignment.prefer_wall > 0 && !flush(true) {
+        penalty = penalty.saturating_add(alignment.prefer_wall);
     }
+    let spec = &shape.spec;
+    let axes = misaligned(x, spec.w) as u32 + misaligned(y, spec.h) as u32;
+    penalty.saturating_add(alignment.prefer_aligned_rows.saturating_mul(axes))
 }
 
 // 1つの候補を生成する処理
@@ -231,39 +357,52 @@ fn build_mask_block(size: usize, x: usize, y: usize, spec: &PatternSpec, mask_bl
 fn try_create_candidate(
     size: usize,
     stones: &[u8],
-    territory: &[u8],
+    sums: &BoardSums,
+    masks: &BoardMasks,
     x: usize,
     y: usize,
-    spec: &PatternSpec,
+    shape: &Shape,
     color: u8,
-    words: usize,
+    alignment: &AlignmentOptions,
+    lookup: Lookup,
 ) -> Option<Cand> {
+    let spec = &shape.spec;
+
     // 外周チェック
-    let (ok, perimeter_opp_cells, penalty_perimeter) =
-        check_perimeter_4_sides(size, stones, territory, x, y, spec.w, spec.h, color);
-    if !ok {
-        return None;
-    }
+    let (perimeter_opp_cells, penalty_perimeter) = match lookup {
+        Lookup::PrefixSums => check_perimeter(size, sums, x, y, shape)?,
+        Lookup::Scan { territory } => scan_perimeter(size, stones, territory, color, x, y, shape)?,
+    };
 
-    // 内部制約チェック
-    if !check_internal_constraint(size, stones, territory, x, y, spec, color) {
+    // 内部制約チェック: 内部は「同色の石 or 同色の地」のみで構成されていること
+    let own = match lookup {
+        Lookup::PrefixSums => shape.count(&sums.own, x, y) == shape.cells,
+        Lookup::Scan { territory } => scan_own(size, stones, territory, color, x, y, shape),
+    };
+    if !own {
         return None;
     }
 
-    // 内部ペナルティ計算とmask生成
-    let (stones_in_rect, penalty_internal, internal_no_stone_cells, mask, mut mask_block) =
-        calculate_internal_penalty_and_masks(size, stones, x, y, spec, words);
+    // 内部ペナルティ計算
+    let (stones_in_rect, penalty_internal, internal_no_stone_cells) =
+        calculate_internal_penalty(size, stones, sums, x, y, shape);
 
-    // mask_blockに4近傍を追加
-    build_mask_block(size, x, y, spec, &mut mask_block);
+    // mask は形を平行移動し、mask_block は4近傍を加える（隣接禁止用）
+    let mask = shape.mask.shift_up(idx2(size, x, y));
+    let mask_block = masks.dilate(&mask);
+
+    let territory_cells = shape.count(&sums.territory, x, y);
+    let move_distance = estimate_movement(size, stones, x, y, spec);
 
     let penalty_total = penalty_perimeter.saturating_add(penalty_internal);
-    let cost = penalty_total;
+    let penalty_alignment = alignment_penalty(size, stones, x, y, shape, color, alignment);
+    let cost = penalty_total.saturating_add(penalty_alignment);
 
     Some(Cand {
+        color,
         x,
         y,
-        spec: *spec,
+        spec: shape.spec.clone(),
         cost,
         stones_in_rect,
         penalty_total,
@@ -271,29 +410,36 @@ fn try_create_candidate(
         penalty_internal,
         perimeter_opp_cells,
         internal_no_stone_cells,
+        penalty_alignment,
+        territory_cells,
+        move_distance,
         mask,
         mask_block,
     })
 }
 
 // 1つのspecに対する候補生成
+#[allow(clippy::too_many_arguments)]
 fn generate_candidates_for_spec(
     size: usize,
     stones: &[u8],
-    territory: &[u8],
-    spec: &PatternSpec,
+    sums: &BoardSums,
+    masks: &BoardMasks,
+    spec: &Arc<PatternSpec>,
     color: u8,
-    words: usize,
+    alignment: &AlignmentOptions,
+    lookup: Lookup,
 ) -> Vec<Cand> {
     let mut cands = Vec::new();
     if size < spec.w || size < spec.h {
         return cands;
     }
+    let shape = Shape::new(size, spec);
     for y in 0..=(size - spec.h) {
         for x in 0..=(size - spec.w) {
-            if let Some(cand) =
-                try_create_candidate(size, stones, territory, x, y, spec, color, words)
-            {
+            if let Some(cand) = try_create_candidate(
+                size, stones, sums, masks, x, y, &shape, color, alignment, lookup,
+            ) {
                 cands.push(cand);
             }
         }
@@ -301,42 +447,92 @@ fn generate_candidates_for_spec(
     cands
 }
 
+// 候補を作るパターン: 主パターンと、端数があるときは該当rの端数パターン（分けてよいなら r 以下すべて）
+fn used_specs(
+    specs: &[Arc<PatternSpec>],
+    remainder: u8,
+    pieces: usize,
+) -> impl Iterator<Item = &Arc<PatternSpec>> {
+    let main = specs.iter().filter(|s| s.slot == PatternSlot::Main);
+    let rem = specs.iter().filter(move |s| match s.slot {
+        PatternSlot::Remainder(r) => {
+            remainder > 0 && (r == remainder || (pieces > 1 && r < remainder))
+        }
+        PatternSlot::Main => false,
+    });
+    main.chain(rem)
+}
+
+/// `generate_candidates` と同じ候補を、外周と内部をセルごとに調べて作る（ベンチマーク用の比較対象）
+pub(crate) fn scan_candidates(
+    size: usize,
+    stones: &[u8],
+    territory: &[u8],
+    color: u8,
+    remainder: u8,
+    pieces: usize,
+    specs: &[Arc<PatternSpec>],
+) -> Vec<Cand> {
+    let sums = BoardSums::new(size, stones, territory, color);
+    let masks = BoardMasks::new(size);
+    let mut cands: Vec<Cand> = used_specs(specs, remainder, pieces)
+        .flat_map(|spec| {
+            generate_candidates_for_spec(
+                size,
+                stones,
+                &sums,
+                &masks,
+                spec,
+                color,
+                &AlignmentOptions::default(),
+                Lookup::Scan { territory },
+            )
+        })
+        .collect();
+    cands.sort_by_key(|c| c.cost);
+    cands
+}
+
+/// ライブラリのパターンを盤面のすべての位置に置いて候補を作る
+///
+/// 主パターンはすべて、端数パターンは端数 `remainder`（1..単位）と同じ大きさのものだけを使います。
+/// 端数を `pieces` 個（2以上）まで分けてよい場合は、端数以下の大きさの端数パターンをすべて使います。
+/// `constraints` の禁止セルにかかる候補と、動かさない石を取り除く候補は作りません。
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn generate_candidates(
     size: usize,
     stones: &[u8],
     territory: &[u8],
     color: u8,
     remainder: u8,
+    pieces: usize,
+    specs: &[Arc<PatternSpec>],
+    alignment: &AlignmentOptions,
+    constraints: &Constraints,
     logger: Option<&dyn Logger>,
 ) -> Vec<Cand> {
-    let n = size * size;
-    #[allow(clippy::manual_div_ceil)]
-    let words = (n + 63) / 64;
-
-    let mut cands: Vec<Cand> = Vec::new();
+    debug_assert!(size * size <= BOARD_WORDS * 64);
+    let sums = BoardSums::new(size, stones, territory, color);
+    let masks = BoardMasks::new(size);
 
-    // 主パターン
-    for spec in PATTERN_SPECS {
-        cands.extend(generate_candidates_for_spec(
-            size, stones, territory, &spec, color, words,
-        ));
-    }
+    let mut cands: Vec<Cand> = used_specs(specs, remainder, pieces)
+        .flat_map(|spec| {
+            generate_candidates_for_spec(
+                size,
+                stones,
+                &sums,
+                &masks,
+                spec,
+                color,
+                alignment,
+                Lookup::PrefixSums,
+            )
+        })
+        .collect();
 
-    // 端数パターン（r=1..9 のときのみ、該当rだけ列挙）
-    if (1..=9).contains(&remainder) {
-        for spec in REMAINDER_SPECS {
-            match spec.slot {
-                PatternSlot::Remainder(r) if r == remainder => {}
-                _ => continue,
-            }
-            // 仕様: 1xN は N>=6 を禁止（端数用の線形パターンを抑制）
-            if spec.kind == PatternKind::Rect1xN && spec.w.max(spec.h) >= 6 {
-                continue;
-            }
-            cands.extend(generate_candidates_for_spec(
-                size, stones, territory, &spec, color, words,
-            ));
-        }
+    // 禁止セル・動かさない石を変えてしまう候補を除く
+    if !constraints.is_empty() {
+        cands.retain(|c| constraints.allows(size, stones, c));
     }
 
     cands.sort_by_key(|c| c.cost);
@@ -350,9 +546,9 @@ pub(crate) fn generate_candidates(
         if log_patterns_enabled() {
             for c in &cands {
                 l.log(&format!(
-                    "[cand] color={color} slot={} spec={} x={} y={} w={} h={} stones={} perimOpp={} innerNoStone={} penPerim={} penInner={} cost={}",
-                    slot_name_pub(c.spec.slot),
-                    spec_name_pub(&c.spec),
+                    "[cand] color={color} slot={} spec={} x={} y={} w={} h={} stones={} perimOpp={} innerNoStone={} penPerim={} penInner={} penAlign={} cost={}",
+                    slot_name(c.spec.slot),
+                    spec_label(&c.spec),
                     c.x,
                     c.y,
                     c.spec.w,
@@ -362,6 +558,7 @@ pub(crate) fn generate_candidates(
                     c.internal_no_stone_cells,
                     c.penalty_perimeter,
                     c.penalty_internal,
+                    c.penalty_alignment,
                     c.cost
                 ));
             }
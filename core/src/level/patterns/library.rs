@@ -0,0 +1,418 @@
+use super::types::{PatternSlot, PatternSpec};
+use crate::types::BOARD_SIZE;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+const DEFAULT_PATTERNS: &str = include_str!("default_patterns.toml");
+
+/// 既定の数える単位（10目ずつ）
+pub const DEFAULT_UNIT: usize = 10;
+/// 数える単位の範囲
+const UNIT_RANGE: std::ops::RangeInclusive<usize> = 2..=30;
+/// 自動生成する主パターンの点数の上限（単位の何倍まで）
+const GENERATED_MAIN_MULTIPLES: usize = 3;
+
+// cells の文字の種類
+const EMPTY: u8 = 0;
+const CELL: u8 = 1;
+const ANCHOR: u8 = 2;
+
+/// 1つの向きのセル（行ごと）
+type Grid = Vec<Vec<u8>>;
+
+/// パターンの用途
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternRole {
+    /// 主パターン（単位の倍数の目数の塊。10目ずつ数えるなら10目・20目・30目など）
+    Main,
+    /// 端数パターン（アンカー以外のセル数が端数になる）
+    Remainder,
+}
+
+/// 整地パターンの定義
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PatternDef {
+    /// 名前（ログ用。向きによらず同じ）
+    pub name: String,
+    pub slot: PatternRole,
+    /// 点数（省略するとアンカー以外のセル数）。主パターンは単位の倍数（3x7 は 20）
+    #[serde(default)]
+    pub points: Option<u32>,
+    /// 上の行から順に並べたセル（"#": セル、"o": アンカーセル、".": パターンに含まない）
+    pub cells: Vec<String>,
+    /// 90度ずつ回した向きも加える
+    #[serde(default)]
+    pub rotate: bool,
+    /// 左右・上下に反転した向きも加える
+    #[serde(default)]
+    pub mirror: bool,
+    /// 同じ形・同じ向きの2枚を長辺をそろえて並べるときは接してよい（2x5）
+    #[serde(default)]
+    pub pair_long_edge: bool,
+}
+
+/// 整地パターンのライブラリ
+///
+/// 主パターン・端数パターンの形を実行時に差し替えます。読み込み時に検証し、
+/// 回転・反転で得られる向きを重複なく展開して候補の列挙に使います。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PatternLibrary {
+    /// 地を数える単位（既定は 10）。主パターンの点数はこの倍数、端数パターンはこれ未満
+    #[serde(default = "default_unit")]
+    pub unit: usize,
+    pub patterns: Vec<PatternDef>,
+}
+
+fn default_unit() -> usize {
+    DEFAULT_UNIT
+}
+
+impl Default for PatternLibrary {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl PatternLibrary {
+    /// 既定のライブラリ（2x5・3x4・3x7・4x5・5x6 と端数パターン）
+    pub fn builtin() -> PatternLibrary {
+        toml::from_str(DEFAULT_PATTERNS).expect("default_patterns.toml is a valid library")
+    }
+
+    /// TOML から読み込んで検証する
+    pub fn from_toml(s: &str) -> Result<PatternLibrary, String> {
+        let library: PatternLibrary =
+            toml::from_str(s).map_err(|e| format!("invalid pattern library: {e}"))?;
+        library.validate()?;
+        Ok(library)
+    }
+
+    /// 単位 `unit` で数えるときの既定のライブラリ
+    ///
+    /// 10 なら [`builtin`](Self::builtin)、それ以外は単位から形を自動生成します。
+    /// 主パターンは点数が単位の1〜3倍の長方形（縦横比3以下。単位そのものの長方形がなければ
+    /// 端数と同じ形）、端数パターンは 1..単位 の各大きさについて
+    /// 1×n（n<=5）・2×(n/2)（偶数）・2×(n/2+1) から角を1つ欠いたもの（奇数）・正方形です。
+    pub fn for_unit(unit: usize) -> Result<PatternLibrary, String> {
+        check_unit(unit)?;
+        if unit == DEFAULT_UNIT {
+            return Ok(Self::builtin());
+        }
+        let mut patterns = Vec::new();
+        for k in 1..=GENERATED_MAIN_MULTIPLES {
+            let mut shapes = rect_shapes(k * unit, PatternRole::Main);
+            if shapes.is_empty() && k == 1 {
+                // 主パターンは端数と違い、正方形以外はすべての向きを使う
+                shapes = compact_shapes(unit, PatternRole::Main)
+                    .into_iter()
+                    .map(|def| PatternDef {
+                        rotate: def.cells.len() != def.cells[0].len(),
+                        ..def
+                    })
+                    .collect();
+            }
+            patterns.extend(shapes);
+        }
+        for r in 1..unit {
+            patterns.extend(compact_shapes(r, PatternRole::Remainder));
+        }
+        let library = PatternLibrary { unit, patterns };
+        library.validate()?;
+        Ok(library)
+    }
+
+    /// JSON から読み込んで検証する
+    pub fn from_json(s: &str) -> Result<PatternLibrary, String> {
+        let library: PatternLibrary =
+            serde_json::from_str(s).map_err(|e| format!("invalid pattern library: {e}"))?;
+        library.validate()?;
+        Ok(library)
+    }
+
+    /// ファイルから読み込んで検証する（拡張子が `.json` なら JSON、それ以外は TOML）
+    pub fn load(path: impl AsRef<Path>) -> Result<PatternLibrary, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    /// 単位・形・点数・端数の大きさを検証する
+    pub fn validate(&self) -> Result<(), String> {
+        self.specs().map(|_| ())
+    }
+
+    /// すべてのパターンの向きを展開する（ライブラリの順、同じパターン内では回転・反転の順）
+    pub(crate) fn specs(&self) -> Result<Vec<Arc<PatternSpec>>, String> {
+        check_unit(self.unit)?;
+        let mut specs = Vec::new();
+        for def in &self.patterns {
+            specs.extend(def.specs(self.unit)?.into_iter().map(Arc::new));
+        }
+        if !self.patterns.iter().any(|p| p.slot == PatternRole::Main) {
+            return Err("pattern library has no main pattern".to_string());
+        }
+        Ok(specs)
+    }
+}
+
+/// ライブラリの向きを展開したパターン（`None` は単位 `unit` の既定のライブラリ）
+pub(crate) fn resolve_specs(
+    library: Option<&PatternLibrary>,
+    unit: usize,
+) -> Result<Vec<Arc<PatternSpec>>, String> {
+    static BUILTIN: OnceLock<Result<Vec<Arc<PatternSpec>>, String>> = OnceLock::new();
+    match library {
+        Some(library) if library.unit != unit => Err(format!(
+            "pattern library counts by {} but the request counts by {unit}",
+            library.unit
+        )),
+        Some(library) => library.specs(),
+        None if unit == DEFAULT_UNIT => BUILTIN
+            .get_or_init(|| PatternLibrary::builtin().specs())
+            .clone(),
+        None => PatternLibrary::for_unit(unit)?.specs(),
+    }
+}
+
+fn check_unit(unit: usize) -> Result<(), String> {
+    if UNIT_RANGE.contains(&unit) {
+        Ok(())
+    } else {
+        Err(format!(
+            "unit must be between {} and {}, got {unit}",
+            UNIT_RANGE.start(),
+            UNIT_RANGE.end()
+        ))
+    }
+}
+
+/// 点数 `points` の長方形（短辺2以上、縦横比3以下）。長辺は盤に収まるものだけ
+fn rect_shapes(points: usize, slot: PatternRole) -> Vec<PatternDef> {
+    (2..)
+        .take_while(|&a| a * a <= points)
+        .filter(|&a| points.is_multiple_of(a))
+        .map(|a| (a, points / a))
+        .filter(|&(a, b)| b <= 3 * a && b <= BOARD_SIZE)
+        .map(|(a, b)| rect_def(a, b, slot))
+        .collect()
+}
+
+/// 大きさ `n` のまとまった形（既定のライブラリの端数パターンと同じ規則）
+fn compact_shapes(n: usize, slot: PatternRole) -> Vec<PatternDef> {
+    let mut defs = Vec::new();
+    if n <= 5 {
+        defs.push(rect_def(1, n, slot));
+    }
+    let half = n / 2;
+    if n.is_multiple_of(2) && half >= 2 {
+        defs.push(PatternDef {
+            rotate: false,
+            ..rect_def(2, half, slot)
+        });
+    } else if !n.is_multiple_of(2) && n >= 3 {
+        let w = half + 1;
+        defs.push(PatternDef {
+            name: format!("2x{w}-1"),
+            slot,
+            points: None,
+            cells: vec![format!(".{}", "#".repeat(w - 1)), "#".repeat(w)],
+            rotate: false,
+            mirror: true,
+            pair_long_edge: false,
+        });
+    }
+    let side = n.isqrt();
+    if side >= 3 && side * side == n {
+        defs.push(PatternDef {
+            rotate: false,
+            ..rect_def(side, side, slot)
+        });
+    }
+    defs
+}
+
+/// a×b の長方形（b 列 a 行、回転あり）。主パターンの細長いもの（短辺2以下）は長辺で並べてよい
+fn rect_def(a: usize, b: usize, slot: PatternRole) -> PatternDef {
+    PatternDef {
+        name: format!("{a}x{b}"),
+        slot,
+        points: None,
+        cells: vec!["#".repeat(b); a],
+        rotate: a != b,
+        mirror: false,
+        pair_long_edge: slot == PatternRole::Main && a <= 2 && a != b,
+    }
+}
+
+impl PatternDef {
+    fn specs(&self, unit: usize) -> Result<Vec<PatternSpec>, String> {
+        let err = |msg: String| format!("pattern {:?}: {msg}", self.name);
+        let grid = self.parse_cells().map_err(err)?;
+        let (w, h) = (grid[0].len(), grid.len());
+        if w > BOARD_SIZE || h > BOARD_SIZE {
+            return Err(err(format!("{w}x{h} does not fit on the board")));
+        }
+
+        // 点数はアンカー以外のセル数（整地しても地の目数は変わらない）
+        let counted = grid.iter().flatten().filter(|&&c| c == CELL).count();
+        if let Some(points) = self.points
+            && points as usize != counted
+        {
+            return Err(err(format!(
+                "points {points} must equal the number of non-anchor cells ({counted})"
+            )));
+        }
+        let slot = match self.slot {
+            PatternRole::Main => {
+                if counted == 0 || !counted.is_multiple_of(unit) {
+                    return Err(err(format!(
+                        "main pattern must have a positive multiple of {unit} non-anchor cells, found {counted}"
+                    )));
+                }
+                PatternSlot::Main
+            }
+            PatternRole::Remainder => {
+                if !(1..unit).contains(&counted) {
+                    return Err(err(format!(
+                        "remainder pattern must have 1..={} non-anchor cells, found {counted}",
+                        unit - 1
+                    )));
+                }
+                PatternSlot::Remainder(counted as u8)
+            }
+        };
+
+        // 回転ごとに反転を試し、同じ形になる向きは1つにまとめる
+        let mirrors: &[(bool, bool)] = if self.mirror {
+            &[(false, false), (true, false), (false, true), (true, true)]
+        } else {
+            &[(false, false)]
+        };
+        let mut grids: Vec<Grid> = Vec::new();
+        let mut g = grid;
+        for _ in 0..if self.rotate { 4 } else { 1 } {
+            for &(fx, fy) in mirrors {
+                let m = flip(&g, fx, fy);
+                if !grids.contains(&m) {
+                    grids.push(m);
+                }
+            }
+            g = rotate(&g);
+        }
+        grids
+            .iter()
+            .map(|g| self.spec(g, slot, counted as u32).map_err(err))
+            .collect()
+    }
+
+    fn parse_cells(&self) -> Result<Grid, String> {
+        let grid: Grid = self
+            .cells
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|ch| match ch {
+                        '#' => Ok(CELL),
+                        'o' => Ok(ANCHOR),
+                        '.' => Ok(EMPTY),
+                        _ => Err(format!("unknown cell {ch:?} (use '#', 'o' or '.')")),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        let w = grid.first().map_or(0, |r| r.len());
+        if w == 0 || grid.iter().any(|r| r.len() != w) {
+            return Err("cells must be non-empty rows of the same length".to_string());
+        }
+        let h = grid.len();
+        let filled = |x: usize, y: usize| grid[y][x] != EMPTY;
+        if !(0..w).any(|x| filled(x, 0))
+            || !(0..w).any(|x| filled(x, h - 1))
+            || !(0..h).any(|y| filled(0, y))
+            || !(0..h).any(|y| filled(w - 1, y))
+        {
+            return Err("cells must not have empty border rows or columns".to_string());
+        }
+
+        // セルが上下左右につながっていること
+        let total = grid.iter().flatten().filter(|&&c| c != EMPTY).count();
+        let start = (0..w).find(|&x| filled(x, 0)).map(|x| (x, 0));
+        let mut seen = vec![vec![false; w]; h];
+        let mut stack: Vec<(usize, usize)> = start.into_iter().collect();
+        let mut reached = 0;
+        while let Some((x, y)) = stack.pop() {
+            if seen[y][x] {
+                continue;
+            }
+            seen[y][x] = true;
+            reached += 1;
+            let neighbors = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for (nx, ny) in neighbors {
+                if nx < w && ny < h && filled(nx, ny) && !seen[ny][nx] {
+                    stack.push((nx, ny));
+                }
+            }
+        }
+        if reached != total {
+            return Err("cells must be 4-connected".to_string());
+        }
+        Ok(grid)
+    }
+
+    /// 1つの向きを PatternSpec にする
+    fn spec(&self, g: &Grid, slot: PatternSlot, points: u32) -> Result<PatternSpec, String> {
+        let (w, h) = (g[0].len(), g.len());
+        let cells: Vec<bool> = g.iter().flatten().map(|&c| c != EMPTY).collect();
+        if self.pair_long_edge && (cells.contains(&false) || w == h) {
+            return Err("pair_long_edge requires a non-square rectangle".to_string());
+        }
+        let anchor_cells = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .filter(|&(x, y)| g[y][x] == ANCHOR)
+            .collect();
+        Ok(PatternSpec {
+            name: self.name.clone(),
+            slot,
+            points,
+            w,
+            h,
+            cells,
+            anchor_cells,
+            pair_long_edge: self.pair_long_edge,
+        })
+    }
+}
+
+/// 時計回りに90度回す（(x, y) → (h-1-y, x)）
+fn rotate(g: &Grid) -> Grid {
+    let (w, h) = (g[0].len(), g.len());
+    (0..w)
+        .map(|x| (0..h).map(|nx| g[h - 1 - nx][x]).collect())
+        .collect()
+}
+
+/// 左右（`fx`）・上下（`fy`）に反転する
+fn flip(g: &Grid, fx: bool, fy: bool) -> Grid {
+    let mut out = g.clone();
+    if fy {
+        out.reverse();
+    }
+    if fx {
+        for row in &mut out {
+            row.reverse();
+        }
+    }
+    out
+}
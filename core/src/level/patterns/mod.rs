@@ -1,10 +1,16 @@
+mod bitboard;
 mod candidate;
-mod specs;
+mod library;
+mod prefix;
 mod types;
 mod utils;
 
-pub(crate) use candidate::generate_candidates;
-pub(crate) use types::{Cand, PatternKind, PatternSlot, PatternSpec};
+pub use library::{DEFAULT_UNIT, PatternDef, PatternLibrary, PatternRole};
+
+pub(crate) use bitboard::Bitboard;
+pub(crate) use candidate::{generate_candidates, scan_candidates};
+pub(crate) use library::resolve_specs;
+pub(crate) use types::{Cand, PatternSlot, PatternSpec};
 pub(crate) use utils::{
-    cell_in_pattern, log_patterns_enabled, long_edge_ok_2x5_only, mask_overlaps,
+    LongEdgeKey, cell_in_pattern, log_patterns_enabled, long_edge_key, spec_label,
 };
@@ -0,0 +1,30 @@
+/// 盤面の2次元累積和（矩形内で条件を満たすセルの数を O(1) で数える）
+pub(crate) struct PrefixSum {
+    stride: usize,
+    /// sums[y * stride + x] は左上 (0, 0)、右下 (x-1, y-1) の矩形内の個数
+    sums: Vec<u32>,
+}
+
+impl PrefixSum {
+    pub(crate) fn new(size: usize, f: impl Fn(usize) -> bool) -> PrefixSum {
+        let stride = size + 1;
+        let mut sums = vec![0u32; stride * stride];
+        for y in 0..size {
+            let mut row = 0u32;
+            for x in 0..size {
+                row += f(y * size + x) as u32;
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row;
+            }
+        }
+        PrefixSum { stride, sums }
+    }
+
+    /// 左上 (x, y)、幅 w、高さ h の矩形内の個数
+    pub(crate) fn count(&self, x: usize, y: usize, w: usize, h: usize) -> u32 {
+        let s = self.stride;
+        let (x1, y1) = (x + w, y + h);
+        self.sums[y1 * s + x1] + self.sums[y * s + x]
+            - self.sums[y * s + x1]
+            - self.sums[y1 * s + x]
+    }
+}
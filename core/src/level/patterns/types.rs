@@ -1,43 +1,43 @@
+use super::bitboard::Bitboard;
+use crate::level::options::Objective;
+use std::sync::Arc;
+
 /// パターンのスロット（主パターン or 端数パターン）
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub(crate) enum PatternSlot {
     Main,
-    /// 端数 r(1..=9) 用
+    /// 端数 r(1..単位) 用
     Remainder(u8),
 }
 
-/// パターンの種類
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub(crate) enum PatternKind {
-    Rect2x5,
-    Rect3x4,
-    Rect3x7,
-    Rect1xN,
-    Rect2xHalf,
-    Rect3x3,
-}
-
-/// パターン仕様
-#[derive(Copy, Clone, Debug)]
+/// パターン仕様（ライブラリのパターンの1つの向き）
+#[derive(Clone, Debug)]
 pub(crate) struct PatternSpec {
+    /// ライブラリでの名前（向きによらず同じ）
+    pub(crate) name: String,
     pub(crate) slot: PatternSlot,
-    pub(crate) kind: PatternKind,
+    /// 点数（アンカー以外のセル数。主パターンはライブラリの単位の倍数）
+    pub(crate) points: u32,
+    /// 外接する長方形の幅と高さ
     pub(crate) w: usize,
     pub(crate) h: usize,
-    /// 0: full, 1: TL, 2: TR, 3: BL, 4: BR（端数の2行パターン用）
-    pub(crate) missing_corner: u8,
+    /// 外接する長方形の各セルがパターンに含まれるか（行優先で w * h 個）
+    pub(crate) cells: Vec<bool>,
     /// アンカーセル（ローカル座標）。
     /// - ペナルティ計算: 石があればペナルティ0、なければペナルティ+1（通常セルは逆）
     /// - 適用時: 石を残す（無ければ追加）
-    pub(crate) anchor_cells: &'static [(usize, usize)],
+    pub(crate) anchor_cells: Vec<(usize, usize)>,
+    /// 同じ向きの2枚を長辺をそろえて並べるときは接してよい
+    pub(crate) pair_long_edge: bool,
 }
 
 /// 候補パターン
 #[derive(Clone)]
 pub(crate) struct Cand {
+    pub(crate) color: u8,
     pub(crate) x: usize,
     pub(crate) y: usize,
-    pub(crate) spec: PatternSpec,
+    pub(crate) spec: Arc<PatternSpec>,
     pub(crate) cost: u32,
     pub(crate) stones_in_rect: u32,
     pub(crate) penalty_total: u32,
@@ -45,7 +45,40 @@ pub(crate) struct Cand {
     pub(crate) penalty_internal: u32,
     pub(crate) perimeter_opp_cells: u32,
     pub(crate) internal_no_stone_cells: u32,
-    pub(crate) mask: Vec<u64>,
-    pub(crate) mask_block: Vec<u64>,
+    /// 並べ方の好みによるペナルティ（`cost` に含む）
+    pub(crate) penalty_alignment: u32,
+    /// パターン内の自分の地のセル数（選ばれると石で埋めずに済む）
+    pub(crate) territory_cells: u32,
+    /// 取り除く石・置く石それぞれの、パターンの外までの距離の合計
+    pub(crate) move_distance: u32,
+    /// パターンのセル
+    pub(crate) mask: Bitboard,
+    /// パターンのセルとその4近傍
+    pub(crate) mask_block: Bitboard,
 }
 
+impl Cand {
+    /// 取り除く石の数（アンカー以外のセルにある石）
+    pub(crate) fn stones_removed(&self) -> u32 {
+        self.penalty_internal - self.internal_no_stone_cells
+    }
+
+    /// 目的関数の係数
+    ///
+    /// 選ばれなかった地は石で埋めるので、移動量の見積もりでは
+    /// パターン内の地のセル数（埋めずに済む数）を差し引きます（定数項は省略）。
+    /// 並べ方の好みによるペナルティはどの目的関数にも加えます。
+    pub(crate) fn objective_cost(&self, objective: Objective) -> f64 {
+        match objective {
+            Objective::Penalty => self.cost as f64,
+            Objective::MovedStones => {
+                (self.stones_removed() + self.internal_no_stone_cells + self.penalty_alignment)
+                    as f64
+                    - self.territory_cells as f64
+            }
+            Objective::EdgeDistance => {
+                (self.move_distance + self.penalty_alignment) as f64 - self.territory_cells as f64
+            }
+        }
+    }
+}
@@ -1,9 +1,128 @@
-use crate::level::ip::solve_select;
-use crate::level::patterns::{PatternSpec, cell_in_pattern, generate_candidates};
-use crate::types::{Logger, idx2};
+use crate::level::constraints::Constraints;
+use crate::level::decompose::{RegionSolution, solve_by_regions};
+use crate::level::ip::{SelectTarget, solve_select};
+use crate::level::options::LevelOptions;
+use crate::level::outcome::{ColorReport, PickedPattern, RegionOutcome, SolveInfo};
+use crate::level::patterns::{
+    Cand, PatternRole, PatternSlot, PatternSpec, cell_in_pattern, generate_candidates,
+    resolve_specs,
+};
+use crate::level::solver::Deadline;
+use crate::types::{Logger, cell_coords, idx2};
+use std::sync::Arc;
 
 /// 選択結果（パターンリストと使用済みセルマスク）
-type SelectResult = (Vec<(usize, usize, PatternSpec)>, Vec<bool>);
+pub(crate) type SelectResult = (Vec<(usize, usize, Arc<PatternSpec>)>, Vec<bool>);
+
+/// パターンの選び方
+pub(crate) struct Selection {
+    /// `targets` と同じ順序の色ごとの選択結果
+    pub(crate) results: Vec<SelectResult>,
+    /// ソルバーの実行結果
+    pub(crate) solve: SolveInfo,
+    /// 領域ごとの結果（領域に分けて解いた場合のみ）
+    pub(crate) regions: Vec<RegionOutcome>,
+    /// `targets` と同じ順序の色ごとの目標と候補の数
+    pub(crate) colors: Vec<ColorReport>,
+    /// 選んだパターン（番号は `LevelReport::new` で振る）
+    pub(crate) patterns: Vec<PickedPattern>,
+}
+
+/// 1色分の整地目標
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ColorTarget {
+    pub(crate) color: u8,
+    /// 地を数える単位
+    pub(crate) unit: usize,
+    /// 主パターンの点数の合計（地を単位で切り捨てた数）
+    pub(crate) main_points: usize,
+    pub(crate) remainder: u8,
+    /// 端数を分けて置いてよいパターンの数の上限
+    pub(crate) pieces: usize,
+    /// 石を置き直すだけで整地する
+    pub(crate) conserve_stones: bool,
+}
+
+impl ColorTarget {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.main_points == 0 && self.remainder == 0
+    }
+}
+
+/// 石の数を保てるか確かめる（保てなければ `Err`）
+///
+/// 整地後は、選んだパターンの点数の分だけの地が石のないセルとして残り、残りの地は石で埋まります。
+/// パターンの内側は同色の石か地だけなので、色ごとの石の数が変わらないのは、
+/// 点数の合計が整地前の石のない同色の地のセル数と一致するときだけで、どのパターンを選んでも変わりません。
+fn check_stones_conserved(stones: &[u8], territory: &[u8], t: &ColorTarget) -> Result<(), String> {
+    let empty_territory = territory
+        .iter()
+        .zip(stones)
+        .filter(|&(&tr, &st)| tr == t.color && st == 0)
+        .count();
+    let points = t.main_points + t.remainder as usize;
+    if points != empty_territory {
+        return Err(format!(
+            "Infeasible: stones of color {} cannot be conserved: patterns count {points} points but {empty_territory} territory cells have no stone",
+            t.color
+        ));
+    }
+    Ok(())
+}
+
+fn picked_pattern(size: usize, c: &Cand) -> PickedPattern {
+    let spec = &c.spec;
+    let cells = (0..spec.h)
+        .flat_map(|dy| (0..spec.w).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| cell_in_pattern(dx, dy, spec))
+        .map(|(dx, dy)| idx2(size, c.x + dx, c.y + dy));
+    let anchors = spec
+        .anchor_cells
+        .iter()
+        .map(|&(dx, dy)| idx2(size, c.x + dx, c.y + dy));
+    PickedPattern {
+        id: 0,
+        color: c.color,
+        name: spec.name.clone(),
+        role: match spec.slot {
+            PatternSlot::Main => PatternRole::Main,
+            PatternSlot::Remainder(_) => PatternRole::Remainder,
+        },
+        points: spec.points,
+        x: c.x as u32,
+        y: c.y as u32,
+        w: spec.w as u32,
+        h: spec.h as u32,
+        cells: cell_coords(size, cells),
+        anchors: cell_coords(size, anchors),
+        cost: c.cost,
+        penalty_perimeter: c.penalty_perimeter,
+        penalty_internal: c.penalty_internal,
+        penalty_alignment: c.penalty_alignment,
+    }
+}
+
+fn build_select_result(size: usize, cands: &[Cand], picked: &[usize], color: u8) -> SelectResult {
+    let mut used = vec![false; size * size];
+    let mut rects: Vec<(usize, usize, Arc<PatternSpec>)> = Vec::new();
+    for &si in picked {
+        let c = &cands[si];
+        if c.color != color {
+            continue;
+        }
+        rects.push((c.x, c.y, c.spec.clone()));
+        for dy in 0..c.spec.h {
+            for dx in 0..c.spec.w {
+                if !cell_in_pattern(dx, dy, &c.spec) {
+                    continue;
+                }
+                let i = idx2(size, c.x + dx, c.y + dy);
+                used[i] = true;
+            }
+        }
+    }
+    (rects, used)
+}
 
 /// 候補からパターンを選択し、使用済みセルをマークする
 ///
@@ -11,56 +130,200 @@ type SelectResult = (Vec<(usize, usize, PatternSpec)>, Vec<bool>);
 /// - `size`: 盤面サイズ
 /// - `stones`: 石の配列
 /// - `territory`: 地の配列
-/// - `color`: 対象色
-/// - `main_target`: 主パターンの目標数
-/// - `remainder`: 端数（1-9）
+/// - `target`: 対象色と整地目標（主パターンの目標数、端数1-9）
+/// - `options`: 整地オプション
+/// - `constraints`: 動かさない石とパターンを置かないセル
+/// - `deadline`: 打ち切り時刻
 /// - `logger`: ログ出力用のLogger（オプション）
 ///
 /// # 戻り値
-/// 選択されたパターンのリストと使用済みセルのマスクを返します。
+/// 選択されたパターンのリストと使用済みセルのマスク、ソルバーの実行結果を返します。
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn select_rects_and_used(
     size: usize,
     stones: &[u8],
     territory: &[u8],
-    color: u8,
-    main_target: usize,
-    remainder: u8,
+    target: ColorTarget,
+    options: &LevelOptions,
+    constraints: &Constraints,
+    deadline: Deadline,
     logger: Option<&dyn Logger>,
-) -> Result<SelectResult, String> {
-    let n = size * size;
-    let used_cells = vec![false; n];
-    let selected: Vec<(usize, usize, PatternSpec)> = Vec::new();
-    let rem_required = if (1..=9).contains(&remainder) {
-        1usize
-    } else {
-        0usize
-    };
-    if main_target == 0 && rem_required == 0 {
-        return Ok((selected, used_cells));
-    }
-
-    let cands = generate_candidates(size, stones, territory, color, remainder, logger);
-    if cands.is_empty() {
-        return Ok((selected, used_cells));
-    }
+) -> Result<(SelectResult, SolveInfo), String> {
+    let mut selection = select_rects_and_used_joint(
+        size,
+        stones,
+        territory,
+        &[target],
+        options,
+        constraints,
+        deadline,
+        logger,
+    )?;
+    Ok((selection.results.remove(0), selection.solve))
+}
 
-    // 主パターン数==main_target、端数パターン数==rem_required を満たす
-    let picked = solve_select(&cands, main_target, rem_required, logger)?;
+/// 複数色の候補を1つのモデルでまとめて選択する
+///
+/// 色の異なるパターン同士は接しないように選びます。
+///
+/// # 引数
+/// - `size`: 盤面サイズ
+/// - `stones`: 石の配列
+/// - `territory`: 地の配列
+/// - `targets`: 色ごとの整地目標
+/// - `options`: 整地オプション
+/// - `constraints`: 動かさない石とパターンを置かないセル
+/// - `deadline`: 打ち切り時刻
+/// - `logger`: ログ出力用のLogger（オプション）
+///
+/// # 戻り値
+/// `targets` と同じ順序の色ごとの選択結果を、ソルバーの実行結果とともに返します。
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn select_rects_and_used_joint(
+    size: usize,
+    stones: &[u8],
+    territory: &[u8],
+    targets: &[ColorTarget],
+    options: &LevelOptions,
+    constraints: &Constraints,
+    deadline: Deadline,
+    logger: Option<&dyn Logger>,
+) -> Result<Selection, String> {
+    let mut selections = select_alternatives(
+        size,
+        stones,
+        territory,
+        targets,
+        options,
+        constraints,
+        1,
+        deadline,
+        logger,
+    )?;
+    Ok(selections.remove(0))
+}
 
-    let mut used = vec![false; n];
-    let mut rects: Vec<(usize, usize, PatternSpec)> = Vec::new();
-    for si in picked {
-        let c = &cands[si];
-        rects.push((c.x, c.y, c.spec));
-        for dy in 0..c.spec.h {
-            for dx in 0..c.spec.w {
-                if !cell_in_pattern(dx, dy, &c.spec) {
-                    continue;
-                }
-                let i = idx2(size, c.x + dx, c.y + dy);
-                used[i] = true;
+/// 選び方を目的関数の小さい順に最大 `k` 通り列挙する
+///
+/// 解を1つ見つけるたびに、その選び方を除外する制約（no-good cut）を加えて解き直します。
+/// `k` が1で `options.decompose` が有効なら、互いに影響しない領域に分けて解きます。
+///
+/// # 戻り値
+/// 選び方を目的関数の小さい順に返します。1つ目の選び方が見つからなければ`Err`を返します。
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn select_alternatives(
+    size: usize,
+    stones: &[u8],
+    territory: &[u8],
+    targets: &[ColorTarget],
+    options: &LevelOptions,
+    constraints: &Constraints,
+    k: usize,
+    deadline: Deadline,
+    logger: Option<&dyn Logger>,
+) -> Result<Vec<Selection>, String> {
+    let specs = resolve_specs(options.patterns.as_ref(), options.unit)?;
+    let mut cands: Vec<Cand> = Vec::new();
+    let mut select_targets: Vec<SelectTarget> = Vec::new();
+    let mut colors: Vec<ColorReport> = Vec::with_capacity(targets.len());
+    for t in targets {
+        colors.push(ColorReport {
+            color: t.color,
+            main_points: t.main_points,
+            remainder: t.remainder,
+            main_candidates: 0,
+            remainder_candidates: 0,
+        });
+        if t.is_empty() {
+            continue;
+        }
+        let color_cands = generate_candidates(
+            size,
+            stones,
+            territory,
+            t.color,
+            t.remainder,
+            t.pieces,
+            &specs,
+            &options.alignment,
+            constraints,
+            logger,
+        );
+        if color_cands.is_empty() {
+            // 制約で候補がなくなった場合は、地をすべて埋めずに整地できないことを返す
+            if !constraints.is_empty() {
+                return Err(format!(
+                    "Infeasible: no pattern for color {} avoids the locked cells and forbidden areas",
+                    t.color
+                ));
             }
+            // 地をすべて石で埋めると数えられなくなるので、整地できないことを返す
+            return Err(format!(
+                "Infeasible: no pattern for color {} fits its territory ({} points, remainder {})",
+                t.color, t.main_points, t.remainder
+            ));
+        }
+        if let Some(report) = colors.last_mut() {
+            report.main_candidates = color_cands
+                .iter()
+                .filter(|c| c.spec.slot == PatternSlot::Main)
+                .count();
+            report.remainder_candidates = color_cands.len() - report.main_candidates;
+        }
+        if t.conserve_stones {
+            check_stones_conserved(stones, territory, t)?;
         }
+        cands.extend(color_cands);
+        select_targets.push(SelectTarget {
+            color: t.color,
+            unit: t.unit,
+            main_points: t.main_points,
+            remainder: t.remainder as usize,
+            pieces: t.pieces,
+            extra_piece_penalty: options.extra_piece_penalty,
+        });
     }
-    Ok((rects, used))
+
+    // 主パターンの点数==main_points、端数パターンの点数==remainder を色ごとに満たす
+    let mut solutions: Vec<RegionSolution> = Vec::new();
+    if select_targets.is_empty() {
+        solutions.push((Vec::new(), SolveInfo::default(), Vec::new()));
+    } else if k <= 1
+        && options.decompose
+        && let Some(solution) = solve_by_regions(
+            size,
+            territory,
+            &cands,
+            &select_targets,
+            options,
+            deadline,
+            logger,
+        )?
+    {
+        solutions.push(solution);
+    } else {
+        let found = solve_select(size, &cands, &select_targets, options, k, deadline, logger)?;
+        solutions.extend(
+            found
+                .into_iter()
+                .map(|(picked, info)| (picked, info, Vec::new())),
+        );
+    }
+
+    Ok(solutions
+        .into_iter()
+        .map(|(picked, solve, regions)| Selection {
+            results: targets
+                .iter()
+                .map(|t| build_select_result(size, &cands, &picked, t.color))
+                .collect(),
+            solve,
+            regions,
+            colors: colors.clone(),
+            patterns: picked
+                .iter()
+                .map(|&i| picked_pattern(size, &cands[i]))
+                .collect(),
+        })
+        .collect())
 }
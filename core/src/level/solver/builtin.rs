@@ -0,0 +1,335 @@
+use super::common::{
+    CapRelaxation, SearchState, TIME_CHECK_INTERVAL, as_count, deadline, infeasible, max_picks,
+};
+use super::{Feasibility, SelectProblem, SelectSolver};
+use crate::level::options::SolverOptions;
+use crate::level::outcome::SolveInfo;
+use std::time::Instant;
+
+/// 組み込みの分枝限定法ソルバー
+///
+/// 貪欲法で初期解を作り、グループごとの線形緩和（分数ナップサック）を下界として深さ優先で探索します。
+/// 重みと目標は非負の整数である必要があります。どのグループにも属さない候補は選びません。
+/// `threads` は無視します。
+pub(super) struct BuiltinSolver;
+
+impl SelectSolver for BuiltinSolver {
+    fn name(&self) -> &'static str {
+        "builtin"
+    }
+
+    fn solve(
+        &self,
+        problem: &SelectProblem,
+        options: &SolverOptions,
+    ) -> Result<(Vec<usize>, SolveInfo), String> {
+        let mut search = Search::new(problem, options)?;
+        let root = search.root_node();
+        let root_bound = match search.bound(&root) {
+            Some(b) => b,
+            None => return Err(infeasible()),
+        };
+        search.greedy(&root);
+        search.branch(root);
+        search.state.finish(root_bound, search.rel_gap)
+    }
+}
+
+/// 実行可能解を1つ探す（見つかった時点で探索をやめる）
+pub(super) fn feasibility(problem: &SelectProblem, time_limit: f64) -> Feasibility {
+    let options = SolverOptions {
+        time_limit: Some(time_limit),
+        ..SolverOptions::default()
+    };
+    let Ok(mut search) = Search::new(problem, &options) else {
+        return Feasibility::Unknown;
+    };
+    search.first_only = true;
+    let root = search.root_node();
+    if search.bound(&root).is_none() {
+        return Feasibility::Infeasible;
+    }
+    search.greedy(&root);
+    if search.state.best.is_none() {
+        search.branch(root);
+    }
+    match search.state.best {
+        Some((_, mut picked)) => {
+            picked.sort_unstable();
+            Feasibility::Feasible(picked)
+        }
+        None if search.state.timed_out => Feasibility::Unknown,
+        None => Feasibility::Infeasible,
+    }
+}
+
+/// 探索ノード（選んだ候補と、グループごとの残り目標・残り選べる数・次に選べる位置）
+#[derive(Clone)]
+struct Node {
+    excluded: Vec<u64>,
+    need: Vec<u32>,
+    picks_left: Vec<u32>,
+    next: Vec<usize>,
+    picked: Vec<usize>,
+    cost: f64,
+}
+
+struct Search<'a> {
+    costs: &'a [f64],
+    /// 下界に使うコスト（上限の制約があれば、ラグランジュ緩和したもの）
+    bound_costs: Vec<f64>,
+    relaxation: Option<CapRelaxation>,
+    /// 候補ごとの (グループ, 重み)
+    member_of: Vec<Option<(usize, u32)>>,
+    /// グループごとの候補（単位重みあたりの `bound_costs`、添字の昇順）
+    orders: Vec<Vec<usize>>,
+    /// 候補ごとの、グループの `orders` の中での位置
+    position: Vec<usize>,
+    targets: Vec<u32>,
+    /// グループごとの選べる候補の数の上限
+    max_picks: Vec<u32>,
+    /// 候補ごとの衝突相手（ビット集合）
+    conflicts: Vec<Vec<u64>>,
+    words: usize,
+    rel_gap: f64,
+    deadline: Option<Instant>,
+    nodes: u64,
+    /// 最初の解が見つかったら探索をやめる
+    first_only: bool,
+    state: SearchState,
+}
+
+impl<'a> Search<'a> {
+    fn new(problem: &'a SelectProblem, options: &SolverOptions) -> Result<Self, String> {
+        let m = problem.costs.len();
+        let words = m.div_ceil(64);
+
+        let (bound_costs, relaxation) = CapRelaxation::new(problem);
+
+        let mut member_of: Vec<Option<(usize, u32)>> = vec![None; m];
+        let mut position = vec![0; m];
+        let mut orders: Vec<Vec<usize>> = Vec::with_capacity(problem.groups.len());
+        let mut targets: Vec<u32> = Vec::with_capacity(problem.groups.len());
+        for (g, group) in problem.groups.iter().enumerate() {
+            let mut order: Vec<usize> = Vec::with_capacity(group.members.len());
+            for &(i, w) in &group.members {
+                if member_of[i].is_some() {
+                    return Err("search solver requires disjoint groups".to_string());
+                }
+                member_of[i] = Some((g, as_count(w, "weights")?));
+                order.push(i);
+            }
+            let ratio = |i: usize| {
+                let w = member_of[i].map_or(1, |(_, w)| w.max(1));
+                bound_costs[i] / w as f64
+            };
+            order.sort_by(|&a, &b| ratio(a).total_cmp(&ratio(b)).then(a.cmp(&b)));
+            for (pos, &i) in order.iter().enumerate() {
+                position[i] = pos;
+            }
+            orders.push(order);
+            targets.push(as_count(group.target, "targets")?);
+        }
+
+        let mut conflicts = vec![vec![0u64; words]; m];
+        for clique in &problem.cliques {
+            let mut bits = vec![0u64; words];
+            for &i in clique {
+                bits[i / 64] |= 1 << (i % 64);
+            }
+            for &i in clique {
+                for (c, b) in conflicts[i].iter_mut().zip(&bits) {
+                    *c |= b;
+                }
+            }
+        }
+
+        Ok(Search {
+            costs: &problem.costs,
+            bound_costs,
+            relaxation,
+            member_of,
+            orders,
+            position,
+            targets,
+            max_picks: max_picks(problem),
+            conflicts,
+            words,
+            rel_gap: options.mip_rel_gap.unwrap_or(0.0).max(0.0),
+            deadline: deadline(options),
+            nodes: 0,
+            first_only: false,
+            state: SearchState::new(problem),
+        })
+    }
+
+    fn root_node(&self) -> Node {
+        let mut excluded = vec![0u64; self.words];
+        // どのグループにも属さない候補は選ばない
+        for (i, m) in self.member_of.iter().enumerate() {
+            if m.is_none() {
+                excluded[i / 64] |= 1 << (i % 64);
+            }
+        }
+        Node {
+            excluded,
+            need: self.targets.clone(),
+            picks_left: self.max_picks.clone(),
+            next: vec![0; self.targets.len()],
+            picked: Vec::new(),
+            cost: 0.0,
+        }
+    }
+
+    fn available(&self, node: &Node, i: usize) -> bool {
+        node.excluded[i / 64] & (1 << (i % 64)) == 0
+    }
+
+    /// このノードから先で選ぶ余地のある候補か
+    fn open(&self, node: &Node, i: usize) -> bool {
+        let Some((g, w)) = self.member_of[i] else {
+            return false;
+        };
+        self.available(node, i)
+            && w > 0
+            && w <= node.need[g]
+            && node.picks_left[g] > 0
+            && self.position[i] >= node.next[g]
+    }
+
+    /// 下界（グループごとに残り目標を単位重みあたりの安い順に分数で埋める）。埋められなければ None
+    ///
+    /// 上限の制約があれば、ラグランジュ緩和したコストで埋めます。
+    fn bound(&self, node: &Node) -> Option<f64> {
+        let mut bound = node.cost;
+        if let Some(relaxation) = &self.relaxation {
+            bound += relaxation.offset(&node.picked);
+        }
+        for (g, order) in self.orders.iter().enumerate() {
+            let mut rest = node.need[g];
+            if rest > 0 && node.picks_left[g] == 0 {
+                return None;
+            }
+            for &i in &order[node.next[g]..] {
+                if rest == 0 {
+                    break;
+                }
+                if !self.available(node, i) {
+                    continue;
+                }
+                let w = self.member_of[i].map_or(1, |(_, w)| w);
+                if w == 0 {
+                    continue;
+                }
+                let take = w.min(rest);
+                bound += self.bound_costs[i] * take as f64 / w as f64;
+                rest -= take;
+            }
+            if rest > 0 {
+                return None;
+            }
+        }
+        Some(bound)
+    }
+
+    /// 候補 i を選んだ子ノード
+    fn pick(&self, node: &Node, g: usize, pos: usize) -> Node {
+        let i = self.orders[g][pos];
+        let w = self.member_of[i].map_or(0, |(_, w)| w);
+        let mut child = node.clone();
+        for (e, c) in child.excluded.iter_mut().zip(&self.conflicts[i]) {
+            *e |= c;
+        }
+        child.excluded[i / 64] |= 1 << (i % 64);
+        child.need[g] -= w;
+        child.picks_left[g] -= 1;
+        child.next[g] = pos + 1;
+        child.picked.push(i);
+        child.cost += self.costs[i];
+        child
+    }
+
+    /// 貪欲法: グループ順に、選べる中で最も安い候補を取っていく
+    fn greedy(&mut self, root: &Node) {
+        let mut node = root.clone();
+        for g in 0..self.orders.len() {
+            while node.need[g] > 0 && node.picks_left[g] > 0 {
+                let pos = (node.next[g]..self.orders[g].len()).find(|&pos| {
+                    let i = self.orders[g][pos];
+                    let w = self.member_of[i].map_or(0, |(_, w)| w);
+                    self.available(&node, i) && w > 0 && w <= node.need[g]
+                });
+                match pos {
+                    Some(pos) => node = self.pick(&node, g, pos),
+                    None => return,
+                }
+            }
+            if node.need[g] > 0 {
+                return;
+            }
+        }
+        self.state.record(node.cost, &node.picked);
+    }
+
+    fn stopped(&self) -> bool {
+        self.state.timed_out || (self.first_only && self.state.best.is_some())
+    }
+
+    fn branch(&mut self, node: Node) {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+            && self.deadline.is_some_and(|d| Instant::now() >= d)
+        {
+            self.state.timed_out = true;
+        }
+        if self.stopped() {
+            return;
+        }
+
+        let Some(bound) = self.bound(&node) else {
+            return;
+        };
+        if self.state.prune(bound, self.rel_gap) {
+            return;
+        }
+        if !self.state.caps.is_empty()
+            && !self
+                .state
+                .caps_reachable(&node.picked, &node.need, |i| self.open(&node, i))
+        {
+            return;
+        }
+
+        // 残り目標のあるグループのうち、選べる候補が最も少ないものを分岐に使う
+        let mut branch_group: Option<(usize, usize)> = None;
+        for (g, order) in self.orders.iter().enumerate() {
+            if node.need[g] == 0 {
+                continue;
+            }
+            let count = order[node.next[g]..]
+                .iter()
+                .filter(|&&i| self.available(&node, i))
+                .count();
+            if branch_group.is_none_or(|(_, c)| count < c) {
+                branch_group = Some((g, count));
+            }
+        }
+        let Some((g, _)) = branch_group else {
+            self.state.record(node.cost, &node.picked);
+            return;
+        };
+
+        for pos in node.next[g]..self.orders[g].len() {
+            let i = self.orders[g][pos];
+            let w = self.member_of[i].map_or(0, |(_, w)| w);
+            if !self.available(&node, i) || w == 0 || w > node.need[g] {
+                continue;
+            }
+            let child = self.pick(&node, g, pos);
+            self.branch(child);
+            if self.stopped() {
+                return;
+            }
+        }
+    }
+}
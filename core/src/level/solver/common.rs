@@ -0,0 +1,276 @@
+use super::{SelectGroup, SelectProblem, stopped_without_solution};
+use crate::level::options::SolverOptions;
+use crate::level::outcome::{SolveInfo, SolveStatus};
+use std::time::{Duration, Instant};
+
+// 制限時間を確認する間隔（ノード数）
+pub(super) const TIME_CHECK_INTERVAL: u64 = 1024;
+// コスト比較の許容誤差
+pub(super) const COST_EPS: f64 = 1e-9;
+
+pub(super) fn infeasible() -> String {
+    "Infeasible: no selection of patterns satisfies the constraints".to_string()
+}
+
+/// 重み・目標を非負の整数に変換する（組み込みの探索ソルバー用）
+pub(super) fn as_count(v: f64, what: &str) -> Result<u32, String> {
+    if v >= 0.0 && v.fract() == 0.0 && v <= u32::MAX as f64 {
+        Ok(v as u32)
+    } else {
+        Err(format!("search solver requires integer {what}: {v}"))
+    }
+}
+
+/// グループごとの選べる候補の数の上限（上限なしは u32::MAX）
+pub(super) fn max_picks(problem: &SelectProblem) -> Vec<u32> {
+    problem
+        .groups
+        .iter()
+        .map(|g| {
+            g.max_picks
+                .map_or(u32::MAX, |n| n.min(u32::MAX as usize) as u32)
+        })
+        .collect()
+}
+
+/// 制限時間から打ち切り時刻を求める
+pub(super) fn deadline(options: &SolverOptions) -> Option<Instant> {
+    options
+        .time_limit
+        .map(|t| Instant::now() + Duration::from_secs_f64(t.max(0.0)))
+}
+
+/// 上限の制約のラグランジュ緩和（目的関数を固定して解き直すとき、下界と分岐の順に効かせる）
+///
+/// 重みの合計が上限以下なら Σ cost_i x_i >= Σ (cost_i + λ w_i) x_i - λ * 上限 が成り立つので、
+/// 重みに λ（候補の数）を掛けてコストに加えたものの下界から `offset` を引いても下界になります。
+pub(super) struct CapRelaxation {
+    weights: Vec<f64>,
+    target: f64,
+    lambda: f64,
+}
+
+impl CapRelaxation {
+    /// 下界に使うコストと、最初の上限の制約の緩和（上限の制約がなければコストそのものと None）
+    pub(super) fn new(problem: &SelectProblem) -> (Vec<f64>, Option<Self>) {
+        let Some(cap) = problem.caps.first() else {
+            return (problem.costs.clone(), None);
+        };
+        let m = problem.costs.len();
+        let mut weights = vec![0.0; m];
+        for &(i, w) in &cap.members {
+            weights[i] += w;
+        }
+        let lambda = m as f64;
+        let costs = (0..m)
+            .map(|i| problem.costs[i] + lambda * weights[i])
+            .collect();
+        let relaxation = CapRelaxation {
+            weights,
+            target: cap.target,
+            lambda,
+        };
+        (costs, Some(relaxation))
+    }
+
+    /// 選んだ候補について、下界に使うコストと元のコストの差から λ * 上限 を引いた値
+    pub(super) fn offset(&self, picked: &[usize]) -> f64 {
+        let sum: f64 = picked.iter().map(|&i| self.weights[i]).sum();
+        self.lambda * (sum - self.target)
+    }
+}
+
+/// 探索の打ち切り状態
+#[derive(Default)]
+pub(super) struct SearchState {
+    /// 制限時間に達した
+    pub(super) timed_out: bool,
+    /// 許容ギャップ以内として枝刈りした
+    pub(super) gap_pruned: bool,
+    /// 最良解（コスト、選んだ候補）
+    pub(super) best: Option<(f64, Vec<usize>)>,
+    /// 除外する解（すべてを含む解は記録しない）
+    pub(super) cuts: Vec<Vec<usize>>,
+    /// 上限の制約（満たさない解は記録しない）
+    pub(super) caps: Vec<CapConstraint>,
+    /// 目的関数の上限（超える解は記録せず、下界が超えれば枝刈りする）
+    objective_cap: Option<f64>,
+}
+
+/// 上限の制約 Σ w_i * x_i <= target
+pub(super) struct CapConstraint {
+    /// 候補ごとの重み（メンバーでなければ 0）
+    weights: Vec<f64>,
+    target: f64,
+    /// 下界に使う、グループごとの (候補, グループでの重み)（グループでの重みあたりの重みの昇順）
+    orders: Vec<Vec<(usize, f64)>>,
+}
+
+impl CapConstraint {
+    fn new(problem: &SelectProblem, group: &SelectGroup) -> Self {
+        let mut weights = vec![0.0; problem.costs.len()];
+        for &(i, w) in &group.members {
+            weights[i] += w;
+        }
+        let orders = problem
+            .groups
+            .iter()
+            .map(|g| {
+                let mut order: Vec<(usize, f64)> = g
+                    .members
+                    .iter()
+                    .copied()
+                    .filter(|&(_, w)| w > 0.0)
+                    .collect();
+                order.sort_by(|a, b| (weights[a.0] / a.1).total_cmp(&(weights[b.0] / b.1)));
+                order
+            })
+            .collect();
+        CapConstraint {
+            weights,
+            target: group.target,
+            orders,
+        }
+    }
+
+    /// 下界（グループごとに残り目標を、選べる候補で重みの小さい順に分数で埋める）。埋められなければ None
+    fn lower_bound(
+        &self,
+        picked: &[usize],
+        need: &[u32],
+        open: &impl Fn(usize) -> bool,
+    ) -> Option<f64> {
+        let mut bound = self.sum(picked);
+        for (order, &need) in self.orders.iter().zip(need) {
+            let mut rest = need as f64;
+            for &(i, w) in order {
+                if rest <= 0.0 {
+                    break;
+                }
+                if !open(i) {
+                    continue;
+                }
+                let take = w.min(rest);
+                bound += self.weights[i] * take / w;
+                rest -= take;
+            }
+            if rest > 0.0 {
+                return None;
+            }
+        }
+        Some(bound)
+    }
+
+    /// 選んだ候補の重みの合計
+    fn sum(&self, picked: &[usize]) -> f64 {
+        picked.iter().map(|&i| self.weights[i]).sum()
+    }
+}
+
+impl SearchState {
+    pub(super) fn new(problem: &SelectProblem) -> Self {
+        SearchState {
+            cuts: problem.cuts.clone(),
+            objective_cap: problem.objective_cap,
+            caps: problem
+                .caps
+                .iter()
+                .map(|c| CapConstraint::new(problem, c))
+                .collect(),
+            ..SearchState::default()
+        }
+    }
+
+    pub(super) fn record(&mut self, cost: f64, picked: &[usize]) {
+        if self.objective_cap.is_some_and(|cap| cost > cap + COST_EPS) {
+            return;
+        }
+        if self
+            .cuts
+            .iter()
+            .any(|cut| cut.iter().all(|i| picked.contains(i)))
+        {
+            return;
+        }
+        if self
+            .caps
+            .iter()
+            .any(|c| c.sum(picked) > c.target + COST_EPS)
+        {
+            return;
+        }
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|(best, _)| cost < best - COST_EPS)
+        {
+            self.best = Some((cost, picked.to_vec()));
+        }
+    }
+
+    /// 上限の制約を、グループの残り目標 `need` をこれから選べる候補（`open`）で埋めて満たせる余地があるか
+    ///
+    /// 残り目標を重みの小さい順に分数で埋めた下界が上限を超えれば、この先どう選んでも満たせません。
+    pub(super) fn caps_reachable(
+        &self,
+        picked: &[usize],
+        need: &[u32],
+        open: impl Fn(usize) -> bool,
+    ) -> bool {
+        self.caps.iter().all(|c| {
+            c.lower_bound(picked, need, &open)
+                .is_some_and(|lo| lo - COST_EPS <= c.target)
+        })
+    }
+
+    /// 下界で枝刈りできるか（最適性を損なわない枝刈りと、ギャップによる枝刈り）
+    pub(super) fn prune(&mut self, bound: f64, rel_gap: f64) -> bool {
+        if self.objective_cap.is_some_and(|cap| bound > cap + COST_EPS) {
+            return true;
+        }
+        let Some((best, _)) = &self.best else {
+            return false;
+        };
+        if bound >= best - COST_EPS {
+            return true;
+        }
+        if bound >= best - rel_gap * best.abs() {
+            self.gap_pruned = true;
+            return true;
+        }
+        false
+    }
+
+    /// 探索結果を選んだ候補（昇順）と実行結果にまとめる
+    pub(super) fn finish(
+        self,
+        root_bound: f64,
+        rel_gap: f64,
+    ) -> Result<(Vec<usize>, SolveInfo), String> {
+        let Some((best_cost, mut picked)) = self.best else {
+            if self.timed_out {
+                return Err(stopped_without_solution(SolveStatus::TimeLimit));
+            }
+            return Err(infeasible());
+        };
+        picked.sort_unstable();
+
+        let gap = ((best_cost - root_bound) / best_cost.abs().max(COST_EPS)).max(0.0);
+        let info = if self.timed_out {
+            SolveInfo {
+                status: SolveStatus::TimeLimit,
+                gap: Some(gap),
+                ..SolveInfo::default()
+            }
+        } else if self.gap_pruned {
+            SolveInfo {
+                status: SolveStatus::GapLimit,
+                gap: Some(gap.min(rel_gap)),
+                ..SolveInfo::default()
+            }
+        } else {
+            SolveInfo::default()
+        };
+        Ok((picked, info))
+    }
+}
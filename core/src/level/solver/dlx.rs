@@ -0,0 +1,358 @@
+use super::common::{CapRelaxation, SearchState, as_count, deadline, infeasible, max_picks};
+use super::{SelectProblem, SelectSolver};
+use crate::level::options::SolverOptions;
+use crate::level::outcome::SolveInfo;
+use std::time::Instant;
+
+// 制限時間を確認する間隔（外したリンク数）
+const TIME_CHECK_WORK: u64 = 4096;
+
+/// Dancing Links（Algorithm X）によるソルバー
+///
+/// 列は「グループ（選ぶ個数の多重度つき主列）」と「セル」「衝突する候補の集合」（どちらも高々1回の副列）で、
+/// 行は候補です。候補を選ぶと、副列を共有する行をリンクから外します。
+/// グループ列の行は単位重みあたりのコストの昇順に並べ、残り目標を安い順に埋めた値を下界として枝刈りします。
+/// `threads` は無視します。
+pub(super) struct DlxSolver;
+
+impl SelectSolver for DlxSolver {
+    fn name(&self) -> &'static str {
+        "exact_cover"
+    }
+
+    fn solve(
+        &self,
+        problem: &SelectProblem,
+        options: &SolverOptions,
+    ) -> Result<(Vec<usize>, SolveInfo), String> {
+        let mut links = Links::new(problem, options)?;
+        let Some(root_bound) = links.bound() else {
+            return Err(infeasible());
+        };
+        links.greedy();
+        links.search();
+        links.state.finish(root_bound, links.rel_gap)
+    }
+}
+
+struct Links<'a> {
+    costs: &'a [f64],
+    /// 下界と行の並びに使うコスト（上限の制約があれば、ラグランジュ緩和したもの）
+    bound_costs: Vec<f64>,
+    relaxation: Option<CapRelaxation>,
+    groups: usize,
+    /// ノードの上下リンク（先頭は列ヘッダ）
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// ノードが属する列
+    col: Vec<usize>,
+    /// ノードが属する行（列ヘッダは usize::MAX）
+    row_of: Vec<usize>,
+    /// 列に残っている行数
+    len: Vec<usize>,
+    /// 行ごとのノード（先頭がグループ列、残りがセル列と衝突の集合の列）
+    row_nodes: Vec<Vec<usize>>,
+    /// 行ごとの (グループ, 重み)
+    member_of: Vec<Option<(usize, u32)>>,
+    hidden: Vec<bool>,
+    /// 外した行（逆順に戻す）
+    trail: Vec<usize>,
+    need: Vec<u32>,
+    /// グループごとの残り選べる数
+    picks_left: Vec<u32>,
+    picked: Vec<usize>,
+    cost: f64,
+    rel_gap: f64,
+    deadline: Option<Instant>,
+    /// 外したリンクの累計（1ノードの重さが盤面で大きく変わるので、作業量で時計を見る）
+    work: u64,
+    next_check: u64,
+    state: SearchState,
+}
+
+impl<'a> Links<'a> {
+    fn new(problem: &'a SelectProblem, options: &SolverOptions) -> Result<Self, String> {
+        let m = problem.costs.len();
+        let groups = problem.groups.len();
+        let cell_count = problem
+            .cells
+            .iter()
+            .flatten()
+            .map(|&c| c + 1)
+            .max()
+            .unwrap_or(0);
+        let cols = groups + cell_count + problem.cliques.len();
+
+        let mut member_of: Vec<Option<(usize, u32)>> = vec![None; m];
+        let mut need: Vec<u32> = Vec::with_capacity(groups);
+        for (g, group) in problem.groups.iter().enumerate() {
+            for &(i, w) in &group.members {
+                if member_of[i].is_some() {
+                    return Err("search solver requires disjoint groups".to_string());
+                }
+                member_of[i] = Some((g, as_count(w, "weights")?));
+            }
+            need.push(as_count(group.target, "targets")?);
+        }
+
+        let mut cliques_of: Vec<Vec<usize>> = vec![Vec::new(); m];
+        for (q, clique) in problem.cliques.iter().enumerate() {
+            for &i in clique {
+                cliques_of[i].push(groups + cell_count + q);
+            }
+        }
+
+        let (bound_costs, relaxation) = CapRelaxation::new(problem);
+        let mut links = Links {
+            costs: &problem.costs,
+            bound_costs,
+            relaxation,
+            groups,
+            up: (0..cols).collect(),
+            down: (0..cols).collect(),
+            col: (0..cols).collect(),
+            row_of: vec![usize::MAX; cols],
+            len: vec![0; cols],
+            row_nodes: vec![Vec::new(); m],
+            member_of,
+            // どのグループにも属さない候補は行を作らない（選ばない）
+            hidden: vec![true; m],
+            trail: Vec::new(),
+            need,
+            picks_left: max_picks(problem),
+            picked: Vec::new(),
+            cost: 0.0,
+            rel_gap: options.mip_rel_gap.unwrap_or(0.0).max(0.0),
+            deadline: deadline(options),
+            work: 0,
+            next_check: TIME_CHECK_WORK,
+            state: SearchState::new(problem),
+        };
+
+        // グループ列の並びが単位重みあたりの `bound_costs` の昇順になるよう、その順に行を追加する
+        let ratio = |i: usize| {
+            let w = links.member_of[i].map_or(1, |(_, w)| w.max(1));
+            links.bound_costs[i] / w as f64
+        };
+        let mut order: Vec<usize> = (0..m).filter(|&i| links.member_of[i].is_some()).collect();
+        order.sort_by(|&a, &b| ratio(a).total_cmp(&ratio(b)).then(a.cmp(&b)));
+        for i in order {
+            let Some((g, _)) = links.member_of[i] else {
+                continue;
+            };
+            links.append_node(i, g);
+            for &cell in &problem.cells[i] {
+                links.append_node(i, groups + cell);
+            }
+            for &c in &cliques_of[i] {
+                links.append_node(i, c);
+            }
+            links.hidden[i] = false;
+        }
+        Ok(links)
+    }
+
+    /// 列 c の末尾に行 r のノードを追加する
+    fn append_node(&mut self, r: usize, c: usize) {
+        let x = self.up.len();
+        let last = self.up[c];
+        self.up.push(last);
+        self.down.push(c);
+        self.col.push(c);
+        self.row_of.push(r);
+        self.down[last] = x;
+        self.up[c] = x;
+        self.len[c] += 1;
+        self.row_nodes[r].push(x);
+    }
+
+    fn hide_row(&mut self, r: usize) {
+        if self.hidden[r] {
+            return;
+        }
+        for &x in &self.row_nodes[r] {
+            let (u, d) = (self.up[x], self.down[x]);
+            self.down[u] = d;
+            self.up[d] = u;
+            self.len[self.col[x]] -= 1;
+        }
+        self.work += self.row_nodes[r].len() as u64;
+        self.hidden[r] = true;
+        self.trail.push(r);
+    }
+
+    /// 列 c に残っている行をすべて外す
+    fn hide_column_rows(&mut self, c: usize) {
+        // 外したノードも下へのリンクを保つので、そのまま列をたどれる
+        let mut x = self.down[c];
+        while x != c {
+            let next = self.down[x];
+            self.hide_row(self.row_of[x]);
+            x = next;
+        }
+    }
+
+    /// trail の長さが mark になるまで、外した行を逆順に戻す
+    fn restore(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            let Some(r) = self.trail.pop() else {
+                break;
+            };
+            for &x in self.row_nodes[r].iter().rev() {
+                let (u, d) = (self.up[x], self.down[x]);
+                self.down[u] = x;
+                self.up[d] = x;
+                self.len[self.col[x]] += 1;
+            }
+            self.hidden[r] = false;
+        }
+    }
+
+    /// 列 c に残っている行
+    fn column_rows(&self, c: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut x = self.down[c];
+        std::iter::from_fn(move || {
+            if x == c {
+                return None;
+            }
+            let r = self.row_of[x];
+            x = self.down[x];
+            Some(r)
+        })
+    }
+
+    fn weight(&self, r: usize) -> u32 {
+        self.member_of[r].map_or(0, |(_, w)| w)
+    }
+
+    /// この先で選ぶ余地のある行か
+    fn open(&self, r: usize) -> bool {
+        !self.hidden[r] && self.member_of[r].is_some_and(|(g, w)| w > 0 && w <= self.need[g])
+    }
+
+    /// 行 r を選び、副列を共有する行をリンクから外す
+    fn choose(&mut self, r: usize, g: usize) {
+        self.hide_row(r);
+        for k in 1..self.row_nodes[r].len() {
+            self.hide_column_rows(self.col[self.row_nodes[r][k]]);
+        }
+        self.need[g] -= self.weight(r);
+        self.picks_left[g] -= 1;
+        if self.need[g] == 0 || self.picks_left[g] == 0 {
+            self.hide_column_rows(g);
+        }
+        self.picked.push(r);
+        self.cost += self.costs[r];
+    }
+
+    fn unchoose(&mut self, r: usize, g: usize, mark: usize) {
+        self.cost -= self.costs[r];
+        self.picked.pop();
+        self.need[g] += self.weight(r);
+        self.picks_left[g] += 1;
+        self.restore(mark);
+    }
+
+    /// 下界（グループごとに残り目標を列の先頭から分数で埋める）。埋められなければ None
+    ///
+    /// 上限の制約があれば、ラグランジュ緩和したコストで埋めます。
+    fn bound(&self) -> Option<f64> {
+        let mut bound = self.cost;
+        if let Some(relaxation) = &self.relaxation {
+            bound += relaxation.offset(&self.picked);
+        }
+        for g in 0..self.groups {
+            let mut rest = self.need[g];
+            for r in self.column_rows(g) {
+                if rest == 0 {
+                    break;
+                }
+                let w = self.weight(r);
+                if w == 0 {
+                    continue;
+                }
+                let take = w.min(rest);
+                bound += self.bound_costs[r] * take as f64 / w as f64;
+                rest -= take;
+            }
+            if rest > 0 {
+                return None;
+            }
+        }
+        Some(bound)
+    }
+
+    /// 貪欲法: グループ順に、各列の先頭（最も安い行）を取っていき初期解にする
+    fn greedy(&mut self) {
+        let mut chosen: Vec<(usize, usize, usize)> = Vec::new();
+        'groups: for g in 0..self.groups {
+            while self.need[g] > 0 {
+                let Some(r) = self
+                    .column_rows(g)
+                    .find(|&r| self.weight(r) <= self.need[g])
+                else {
+                    break 'groups;
+                };
+                chosen.push((r, g, self.trail.len()));
+                self.choose(r, g);
+            }
+        }
+        if self.need.iter().all(|&n| n == 0) {
+            self.state.record(self.cost, &self.picked);
+        }
+        for (r, g, mark) in chosen.into_iter().rev() {
+            self.unchoose(r, g, mark);
+        }
+    }
+
+    fn search(&mut self) {
+        if self.work >= self.next_check {
+            self.next_check = self.work + TIME_CHECK_WORK;
+            if self.deadline.is_some_and(|d| Instant::now() >= d) {
+                self.state.timed_out = true;
+            }
+        }
+        if self.state.timed_out {
+            return;
+        }
+
+        // 残り目標のある主列のうち、行が最も少ないものを選ぶ（MRV）
+        let Some(g) = (0..self.groups)
+            .filter(|&g| self.need[g] > 0)
+            .min_by_key(|&g| (self.len[g], g))
+        else {
+            self.state.record(self.cost, &self.picked);
+            return;
+        };
+        let Some(bound) = self.bound() else {
+            return;
+        };
+        if self.state.prune(bound, self.rel_gap) {
+            return;
+        }
+        if !self.state.caps.is_empty()
+            && !self
+                .state
+                .caps_reachable(&self.picked, &self.need, |r| self.open(r))
+        {
+            return;
+        }
+
+        // 先頭（最も安い行）から試し、試し終えた行は外して同じ組合せを二度数えない
+        let mark = self.trail.len();
+        while self.down[g] != g {
+            let r = self.row_of[self.down[g]];
+            if self.weight(r) <= self.need[g] {
+                let inner = self.trail.len();
+                self.choose(r, g);
+                self.search();
+                self.unchoose(r, g, inner);
+                if self.state.timed_out {
+                    break;
+                }
+            }
+            self.hide_row(r);
+        }
+        self.restore(mark);
+    }
+}
@@ -0,0 +1,76 @@
+use super::lp::{build_model, picked_indices};
+use super::{SelectProblem, SelectSolver, stopped_without_solution};
+use crate::level::options::SolverOptions;
+use crate::level::outcome::{SolveInfo, SolveStatus};
+use ::highs::{HighsModelStatus, HighsSolutionStatus};
+use good_lp::ResolutionError;
+use good_lp::solvers::highs::HighsProblem;
+
+/// HiGHS で解くソルバー
+pub(super) struct HighsSolver;
+
+impl SelectSolver for HighsSolver {
+    fn name(&self) -> &'static str {
+        "highs"
+    }
+
+    fn solve(
+        &self,
+        problem: &SelectProblem,
+        options: &SolverOptions,
+    ) -> Result<(Vec<usize>, SolveInfo), String> {
+        let (model, _) = build_model(problem, good_lp::highs);
+        let (values, info) = run_highs(model, options)?;
+        Ok((picked_indices(values), info))
+    }
+}
+
+/// HiGHS で解き、変数の値（変数の追加順）と実行結果を返す
+///
+/// 制限時間やギャップで打ち切った場合も、実行可能解があればそれを返します。
+fn run_highs(model: HighsProblem, solver: &SolverOptions) -> Result<(Vec<f64>, SolveInfo), String> {
+    let mut model = model.into_inner();
+    if let Some(time_limit) = solver.time_limit {
+        model.set_option("time_limit", time_limit);
+    }
+    if let Some(gap) = solver.mip_rel_gap {
+        model.set_option("mip_rel_gap", gap);
+    }
+    if let Some(threads) = solver.threads {
+        model.set_option("threads", threads as i32);
+    }
+
+    let solved = model.solve();
+    let status = match solved.status() {
+        HighsModelStatus::Optimal
+        | HighsModelStatus::ObjectiveBound
+        | HighsModelStatus::ObjectiveTarget => {
+            let gap = solved.mip_gap();
+            if gap.is_finite() && gap > 0.0 {
+                SolveStatus::GapLimit
+            } else {
+                SolveStatus::Optimal
+            }
+        }
+        HighsModelStatus::ReachedTimeLimit
+        | HighsModelStatus::ReachedSolutionLimit
+        | HighsModelStatus::ReachedInterrupt
+        | HighsModelStatus::ReachedIterationLimit
+        | HighsModelStatus::ReachedMemoryLimit => SolveStatus::TimeLimit,
+        HighsModelStatus::Infeasible | HighsModelStatus::UnboundedOrInfeasible => {
+            return Err(ResolutionError::Infeasible.to_string());
+        }
+        other => return Err(format!("solver failed: {other:?}")),
+    };
+    if solved.primal_solution_status() != HighsSolutionStatus::Feasible {
+        return Err(stopped_without_solution(status));
+    }
+
+    let gap = solved.mip_gap();
+    let info = SolveInfo {
+        status,
+        gap: (status != SolveStatus::Optimal && gap.is_finite()).then_some(gap),
+        ..SolveInfo::default()
+    };
+    Ok((solved.get_solution().columns().to_vec(), info))
+}
@@ -0,0 +1,82 @@
+use super::SelectProblem;
+use good_lp::{Expression, ProblemVariables, Solver, SolverModel, Variable, constraint, variable};
+
+/// good_lp のモデルを組み立てる
+///
+/// 変数は候補の順に追加するので、返す変数・ソルバーの列の並びは候補の添字と一致します。
+pub(super) fn build_model<S: Solver>(
+    problem: &SelectProblem,
+    solver: S,
+) -> (S::Model, Vec<Variable>) {
+    let mut pb = ProblemVariables::new();
+    let xs = (0..problem.costs.len())
+        .map(|_| pb.add(variable().binary()))
+        .collect::<Vec<_>>();
+
+    let mut obj: Expression = 0.0.into();
+    for (i, &cost) in problem.costs.iter().enumerate() {
+        obj += cost * xs[i];
+    }
+    let mut model = pb.minimise(obj).using(solver);
+
+    for group in &problem.groups {
+        let mut sum: Expression = 0.0.into();
+        for &(i, w) in &group.members {
+            sum += w * xs[i];
+        }
+        let sum_le = sum.clone();
+        model = model.with(constraint!(sum_le <= group.target));
+        model = model.with(constraint!(sum >= group.target));
+        if let Some(max) = group.max_picks {
+            let mut picks: Expression = 0.0.into();
+            for &(i, _) in &group.members {
+                picks += xs[i];
+            }
+            model = model.with(constraint!(picks <= max as f64));
+        }
+    }
+
+    for cap in &problem.caps {
+        let mut sum: Expression = 0.0.into();
+        for &(i, w) in &cap.members {
+            sum += w * xs[i];
+        }
+        model = model.with(constraint!(sum <= cap.target));
+    }
+
+    if let Some(cap) = problem.objective_cap {
+        let mut sum: Expression = 0.0.into();
+        for (i, &cost) in problem.costs.iter().enumerate() {
+            sum += cost * xs[i];
+        }
+        model = model.with(constraint!(sum <= cap));
+    }
+
+    for clique in &problem.cliques {
+        let mut sum: Expression = 0.0.into();
+        for &i in clique {
+            sum += xs[i];
+        }
+        model = model.with(constraint!(sum <= 1));
+    }
+
+    for cut in &problem.cuts {
+        let mut sum: Expression = 0.0.into();
+        for &i in cut {
+            sum += xs[i];
+        }
+        model = model.with(constraint!(sum <= cut.len() as f64 - 1.0));
+    }
+
+    (model, xs)
+}
+
+/// 変数の値から選ばれた候補の添字を取り出す
+pub(super) fn picked_indices(values: impl IntoIterator<Item = f64>) -> Vec<usize> {
+    values
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, v)| v.is_finite() && v > 0.5)
+        .map(|(i, _)| i)
+        .collect()
+}
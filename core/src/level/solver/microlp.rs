@@ -0,0 +1,28 @@
+use super::lp::{build_model, picked_indices};
+use super::{SelectProblem, SelectSolver};
+use crate::level::options::SolverOptions;
+use crate::level::outcome::SolveInfo;
+use good_lp::{Solution, SolverModel};
+
+/// microlp（純Rust）で解くソルバー
+///
+/// microlp には打ち切り条件がないため、ギャップとスレッド数は無視して最適解まで解きます。
+/// 制限時間は止められないので、`LevelOptions::validate` で指定を拒みます。
+pub(super) struct MicrolpSolver;
+
+impl SelectSolver for MicrolpSolver {
+    fn name(&self) -> &'static str {
+        "microlp"
+    }
+
+    fn solve(
+        &self,
+        problem: &SelectProblem,
+        _options: &SolverOptions,
+    ) -> Result<(Vec<usize>, SolveInfo), String> {
+        let (model, xs) = build_model(problem, good_lp::microlp);
+        let solution = model.solve().map_err(|e| e.to_string())?;
+        let picked = picked_indices(xs.iter().map(|&x| solution.value(x)));
+        Ok((picked, SolveInfo::default()))
+    }
+}
@@ -0,0 +1,250 @@
+mod builtin;
+mod common;
+mod dlx;
+#[cfg(feature = "highs")]
+mod highs;
+#[cfg(any(feature = "highs", feature = "microlp"))]
+mod lp;
+#[cfg(feature = "microlp")]
+mod microlp;
+
+use crate::level::options::{SolverBackend, SolverOptions};
+use crate::level::outcome::{SolveInfo, SolveStatus};
+use std::time::Instant;
+
+/// 重み付き個数の等式制約 Σ w_i * x_i == target（と、選ぶ数の上限 Σ x_i <= max_picks）
+#[derive(Clone)]
+pub(crate) struct SelectGroup {
+    /// (候補の添字, 重み)
+    pub(crate) members: Vec<(usize, f64)>,
+    pub(crate) target: f64,
+    /// 選べる候補の数の上限（None は上限なし）
+    pub(crate) max_picks: Option<usize>,
+}
+
+/// パターン選択問題（0-1整数計画）
+///
+/// min Σ cost_i * x_i
+/// s.t. 各グループの等式制約と選ぶ数の上限、衝突する候補の集合ごとに Σ_{i∈clique} x_i <= 1、除外する解
+#[derive(Clone)]
+pub(crate) struct SelectProblem {
+    pub(crate) costs: Vec<f64>,
+    pub(crate) groups: Vec<SelectGroup>,
+    /// 同時に高々1つしか選べない候補の集合（衝突する組はいずれかの集合に含まれる）
+    pub(crate) cliques: Vec<Vec<usize>>,
+    /// 候補ごとの占有セル（Dancing Links の副列。重なる候補の組は `cliques` にも含まれる）
+    pub(crate) cells: Vec<Vec<usize>>,
+    /// 除外する解（no-good cut: Σ_{i∈cut} x_i <= |cut| - 1）
+    pub(crate) cuts: Vec<Vec<usize>>,
+    /// 上限の制約 Σ w_i * x_i <= target（解き直すときに目的関数を最適値に固定する。`max_picks` は使わない）
+    pub(crate) caps: Vec<SelectGroup>,
+    /// 目的関数の上限（超える解は求めない）
+    pub(crate) objective_cap: Option<f64>,
+}
+
+impl SelectProblem {
+    /// 候補の一部だけの部分問題（グループの目標は `targets` に置き換える）
+    ///
+    /// 添字は `members`（昇順）内の位置になります。衝突の集合は `members` に含まれる分だけにし、
+    /// 除外する解と上限の制約は含めません。
+    pub(crate) fn restrict(&self, members: &[usize], targets: &[f64]) -> SelectProblem {
+        let mut local = vec![usize::MAX; self.costs.len()];
+        for (l, &i) in members.iter().enumerate() {
+            local[i] = l;
+        }
+        let groups = self
+            .groups
+            .iter()
+            .zip(targets)
+            .map(|(group, &target)| SelectGroup {
+                members: group
+                    .members
+                    .iter()
+                    .filter(|&&(i, _)| local[i] != usize::MAX)
+                    .map(|&(i, w)| (local[i], w))
+                    .collect(),
+                target,
+                max_picks: group.max_picks,
+            })
+            .collect();
+        let cliques = self
+            .cliques
+            .iter()
+            .map(|q| {
+                q.iter()
+                    .filter(|&&i| local[i] != usize::MAX)
+                    .map(|&i| local[i])
+                    .collect::<Vec<_>>()
+            })
+            .filter(|q| q.len() >= 2)
+            .collect();
+        SelectProblem {
+            costs: members.iter().map(|&i| self.costs[i]).collect(),
+            groups,
+            cliques,
+            cells: members.iter().map(|&i| self.cells[i].clone()).collect(),
+            cuts: Vec::new(),
+            caps: Vec::new(),
+            objective_cap: None,
+        }
+    }
+}
+
+impl SelectProblem {
+    /// 選んだ候補の目的関数値
+    pub(crate) fn objective(&self, picked: &[usize]) -> f64 {
+        picked.iter().map(|&i| self.costs[i]).sum()
+    }
+}
+
+// 目的関数を固定するときの許容誤差（コストの差はこれよりずっと大きい）
+const CAP_TOLERANCE: f64 = 1e-6;
+
+/// 同じコストの解のうち、候補の順位で決まる1つを選び直す
+///
+/// `picked` のコストを上限に固定し、順位（`ranks`、候補ごとに異なる整数）の合計を最小にする解を
+/// 許容ギャップ 0 で解き直します。順位の合計が同じ解が他にもあれば、除外しながらすべて求め、
+/// 順位を昇順に並べた列が辞書順で最小のものを返します。目的関数が整数なので、ソルバーの許容誤差に
+/// よらず同じ解になります。解き直しが打ち切られた・失敗した場合は、それまでに見つけた解を返します。
+pub(crate) fn canonicalize(
+    backend: &dyn SelectSolver,
+    problem: &SelectProblem,
+    ranks: &[u32],
+    picked: Vec<usize>,
+    options: &SolverOptions,
+    deadline: Deadline,
+) -> Vec<usize> {
+    let objective = problem.objective(&picked);
+    let rank_sum = |picked: &[usize]| picked.iter().map(|&i| ranks[i] as f64).sum::<f64>();
+    // 元の目的関数を `picked` のコストに、順位の合計を `picked` の合計に抑える（後者は探索の枝刈りに効く）
+    let mut ranked = SelectProblem {
+        costs: ranks.iter().map(|&r| r as f64).collect(),
+        caps: vec![SelectGroup {
+            members: problem.costs.iter().copied().enumerate().collect(),
+            target: objective + CAP_TOLERANCE * objective.abs().max(1.0),
+            max_picks: None,
+        }],
+        objective_cap: Some(rank_sum(&picked) + 0.5),
+        ..problem.clone()
+    };
+    let options = SolverOptions {
+        mip_rel_gap: Some(0.0),
+        ..options.clone()
+    };
+    let solve =
+        |problem: &SelectProblem| backend.solve(problem, &deadline.solver_options(&options));
+    let key = |picked: &[usize]| {
+        let mut key: Vec<u32> = picked.iter().map(|&i| ranks[i]).collect();
+        key.sort_unstable();
+        key
+    };
+
+    let Ok((mut best, info)) = solve(&ranked) else {
+        return picked;
+    };
+    if info.status != SolveStatus::Optimal {
+        return best;
+    }
+    // 順位の合計が同じ解を、見つけた解を除外しながらすべて求める
+    ranked.objective_cap = Some(rank_sum(&best) + 0.5);
+    let mut found = best.clone();
+    loop {
+        ranked.cuts.push(found);
+        let Ok((next, info)) = solve(&ranked) else {
+            break;
+        };
+        if key(&next) < key(&best) {
+            best = next.clone();
+        }
+        if info.status != SolveStatus::Optimal {
+            break;
+        }
+        found = next;
+    }
+    best
+}
+
+/// 打ち切り時刻（1回の整地で何度も解くときは、`time_limit` をこの時刻までの残り時間として分け合う）
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// `options.time_limit` 秒後（指定がなければ打ち切らない）
+    pub(crate) fn new(options: &SolverOptions) -> Self {
+        Deadline(common::deadline(options))
+    }
+
+    /// 残りの秒数（打ち切らない場合は None）
+    pub(crate) fn remaining(&self) -> Option<f64> {
+        self.0
+            .map(|d| d.saturating_duration_since(Instant::now()).as_secs_f64())
+    }
+
+    /// 制限時間を残り時間に置き換えたソルバーのオプション
+    pub(crate) fn solver_options(&self, options: &SolverOptions) -> SolverOptions {
+        SolverOptions {
+            time_limit: self.remaining().or(options.time_limit),
+            ..options.clone()
+        }
+    }
+}
+
+/// パターン選択問題を解くソルバー
+pub(crate) trait SelectSolver {
+    /// ログ用の名前
+    fn name(&self) -> &'static str;
+
+    /// 問題を解き、選んだ候補の添字（昇順）と実行結果を返す
+    fn solve(
+        &self,
+        problem: &SelectProblem,
+        options: &SolverOptions,
+    ) -> Result<(Vec<usize>, SolveInfo), String>;
+}
+
+/// 実行可能性の判定結果
+pub(crate) enum Feasibility {
+    /// 実行可能（見つかった解の候補の添字）
+    Feasible(Vec<usize>),
+    Infeasible,
+    /// 制限時間内に判定できなかった
+    Unknown,
+}
+
+/// 目的関数を無視して実行可能解を1つ探す（組み込みの分枝限定法を使う）
+pub(crate) fn check_feasibility(problem: &SelectProblem, time_limit: f64) -> Feasibility {
+    builtin::feasibility(problem, time_limit)
+}
+
+/// 整地・ソルバーのエラーが「解がない」ことを表すか（打ち切りや失敗と区別する）
+pub fn is_infeasible(error: &str) -> bool {
+    error.starts_with("Infeasible")
+}
+
+const STOPPED: &str = "no feasible solution found before the solver stopped";
+
+/// 打ち切りまでに実行可能解が見つからなかったときのエラー
+pub(crate) fn stopped_without_solution(status: SolveStatus) -> String {
+    format!("{STOPPED} ({status:?})")
+}
+
+/// 整地・ソルバーのエラーが、制限時間などの打ち切りまでに解が見つからなかったことを表すか
+pub fn is_stopped(error: &str) -> bool {
+    error.starts_with(STOPPED)
+}
+
+/// 指定されたソルバーを返す（feature が無効なら `Err`）
+pub(crate) fn select_solver(backend: SolverBackend) -> Result<Box<dyn SelectSolver>, String> {
+    match backend {
+        #[cfg(feature = "highs")]
+        SolverBackend::Highs => Ok(Box::new(highs::HighsSolver)),
+        #[cfg(feature = "microlp")]
+        SolverBackend::Microlp => Ok(Box::new(microlp::MicrolpSolver)),
+        SolverBackend::Builtin => Ok(Box::new(builtin::BuiltinSolver)),
+        SolverBackend::ExactCover => Ok(Box::new(dlx::DlxSolver)),
+        #[allow(unreachable_patterns)]
+        other => Err(format!(
+            "solver backend {other:?} is not enabled in this build"
+        )),
+    }
+}
@@ -3,9 +3,22 @@ mod level;
 mod matching;
 mod noise;
 mod rng;
+mod search;
 mod types;
 
-pub use crate::generate::generate_board_state;
-pub use crate::level::level_board;
+pub use crate::generate::{
+    GenerateOptions, generate_board_state, generate_board_state_from_seed,
+    generate_board_state_seeded, generate_board_state_with_options, generate_levelable_board_state,
+};
+pub use crate::level::{
+    AlignmentOptions, CellRect, ColorReport, ConflictModelStats, DEFAULT_UNIT, Diagnostic,
+    LevelMode, LevelOptions, LevelOutcome, LevelReport, Objective, PatternDef, PatternLibrary,
+    PatternRole, PickedPattern, RegionOutcome, SolveInfo, SolveStatus, SolverBackend,
+    SolverOptions, count_candidates, count_candidates_by_scan, diagnose_leveling, is_infeasible,
+    is_stopped, level_board, level_board_alternatives, level_board_with_options,
+    solve_conflict_model,
+};
 pub use crate::matching::compute_stone_moves;
-pub use crate::types::{BOARD_SIZE, BoardState, Logger, StoneMove};
+pub use crate::rng::{BoardRng, SeedableBoardRng, SplitMix64, XorShift32, seed_from_str};
+pub use crate::search::{ColorQuery, CountRange, SeedQuery, search_seeds};
+pub use crate::types::{BOARD_SIZE, BoardState, Logger, Seed, StoneMove};
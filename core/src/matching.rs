@@ -1,4 +1,6 @@
-use crate::types::{BOARD_SIZE, BoardState, StoneMove, collect_stone_positions};
+use crate::types::{
+    BOARD_SIZE, BoardState, StoneMove, collect_stone_positions, idx2, is_dead_stone,
+};
 
 /// ハンガリアン法で最小コストマッチングを求める
 /// コスト行列は正方行列である必要がある
@@ -96,7 +98,10 @@ pub fn compute_stone_moves(
     if size != BOARD_SIZE || after.size as usize != size {
         return Err("only 19x19 supported".to_string());
     }
-    if before.stones.len() != size * size || after.stones.len() != size * size {
+    if before.stones.len() != size * size
+        || before.territory.len() != size * size
+        || after.stones.len() != size * size
+    {
         return Err("invalid board arrays length".to_string());
     }
 
@@ -104,8 +109,11 @@ pub fn compute_stone_moves(
 
     // 各色（黒・白）について処理
     for color in [1u8, 2u8] {
-        // 移動前の石の位置を収集
-        let before_positions = collect_stone_positions(&before.stones, size, color);
+        // 移動前の石の位置を収集（死石は取り上げられるので移動の対象外）
+        let before_positions: Vec<_> = collect_stone_positions(&before.stones, size, color)
+            .into_iter()
+            .filter(|&(x, y)| !is_dead_stone(&before.stones, &before.territory, idx2(size, x, y)))
+            .collect();
 
         // 移動後の石の位置を収集
         let after_positions = collect_stone_positions(&after.stones, size, color);
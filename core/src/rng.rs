@@ -11,11 +11,100 @@ pub(crate) fn xorshift32(mut x: u32) -> u32 {
     x
 }
 
-pub(crate) fn next_u32(state: &mut u32) -> u32 {
-    *state = xorshift32(*state);
-    *state
+/// 盤面生成に使う乱数生成器
+///
+/// 生成処理は `next_u32` だけに依存するので、実装を差し替えても
+/// 同じ乱数列なら同じ盤面になります。
+pub trait BoardRng {
+    fn next_u32(&mut self) -> u32;
+
+    /// 1/n の確率で true
+    fn chance_1_in(&mut self, n: u32) -> bool {
+        self.next_u32().is_multiple_of(n)
+    }
+
+    /// 0..n の一様乱数（n>0）
+    fn below(&mut self, n: u32) -> u32 {
+        self.next_u32() % n
+    }
+}
+
+/// 64bitシードから初期化できる乱数生成器
+pub trait SeedableBoardRng: BoardRng + Sized {
+    fn from_seed(seed: u64) -> Self;
+}
+
+/// 従来の32bit xorshift（`generate_board_state(u32)` 用）
+#[derive(Clone, Debug)]
+pub struct XorShift32 {
+    state: u32,
 }
 
-pub(crate) fn rand_chance_1_in(state: &mut u32, n: u32) -> bool {
-    (next_u32(state) % n) == 0
+impl XorShift32 {
+    pub fn new(state: u32) -> Self {
+        Self { state }
+    }
+}
+
+impl BoardRng for XorShift32 {
+    fn next_u32(&mut self) -> u32 {
+        self.state = xorshift32(self.state);
+        self.state
+    }
+}
+
+impl SeedableBoardRng for XorShift32 {
+    fn from_seed(seed: u64) -> Self {
+        Self::new((seed ^ (seed >> 32)) as u32)
+    }
+}
+
+/// 既定の64bit RNG（SplitMix64）
+///
+/// 状態に 0x9e37_79b9_7f4a_7c15 を足し、次の定数で混ぜた値を出力します。
+/// `next_u32` は64bit出力の上位32bitです。この定義は盤面の再現性のため変更しません。
+/// ```text
+/// z = (z ^ (z >> 30)) * 0xbf58_476d_1ce4_e5b9
+/// z = (z ^ (z >> 27)) * 0x94d0_49bb_1331_11eb
+/// z =  z ^ (z >> 31)
+/// ```
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl BoardRng for SplitMix64 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+}
+
+impl SeedableBoardRng for SplitMix64 {
+    fn from_seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+/// 文字列シードを64bitに変換する（FNV-1a 64bit、UTF-8バイト列に対して計算）
+///
+/// プラットフォームやRustのバージョンに依存しない固定のハッシュです。
+pub fn seed_from_str(s: &str) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut h = FNV_OFFSET_BASIS;
+    for b in s.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(FNV_PRIME);
+    }
+    h
 }
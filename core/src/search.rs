@@ -0,0 +1,151 @@
+use crate::generate::{GenerateOptions, generate_board_state_with_options};
+use crate::level::{LevelOptions, level_board_with_options};
+use crate::types::{BOARD_SIZE, BoardState, collect_territory_regions, count_territory};
+use serde::{Deserialize, Serialize};
+use std::thread;
+
+// 並列探索で一度に評価するシード数（結果を昇順で確定させる単位）
+const SEARCH_BATCH_SIZE: u32 = 1024;
+
+/// 数の範囲条件（min/max は両端を含む。None は無制限）
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CountRange {
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+}
+
+impl CountRange {
+    fn contains(&self, v: usize) -> bool {
+        self.min.is_none_or(|m| v >= m) && self.max.is_none_or(|m| v <= m)
+    }
+}
+
+/// 1色分の検索条件
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorQuery {
+    /// 地の合計
+    pub territory: CountRange,
+    /// 地の連結成分（4近傍）の数
+    pub regions: CountRange,
+    /// すべての連結成分の大きさ
+    pub region_size: CountRange,
+    /// 端数（地の合計を整地オプションの単位で割った余り）
+    pub remainder: Option<u8>,
+}
+
+impl ColorQuery {
+    fn matches(&self, board: &BoardState, color: u8, unit: usize) -> bool {
+        let tcount = count_territory(&board.territory, color);
+        if !self.territory.contains(tcount) {
+            return false;
+        }
+        if self.remainder.is_some_and(|r| tcount % unit != r as usize) {
+            return false;
+        }
+        let regions = collect_territory_regions(&board.territory, BOARD_SIZE, color);
+        self.regions.contains(regions.len())
+            && regions.iter().all(|r| self.region_size.contains(r.len()))
+    }
+}
+
+/// シード検索の条件（指定したものをすべて満たす盤面を探す）
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeedQuery {
+    pub black: ColorQuery,
+    pub white: ColorQuery,
+    /// 整地できるかどうか（Noneなら問わない）
+    pub levelable: Option<bool>,
+}
+
+impl SeedQuery {
+    /// 盤面が条件を満たすか判定する
+    ///
+    /// 端数は `level.unit` で数え、整地の可否は `level` で整地して判定します。
+    /// 整地の可否は他の条件をすべて満たしたときだけ計算します。
+    pub fn matches(&self, board: &BoardState, level: &LevelOptions) -> bool {
+        let unit = level.unit;
+        if !self.black.matches(board, 1, unit) || !self.white.matches(board, 2, unit) {
+            return false;
+        }
+        match self.levelable {
+            Some(expected) => {
+                level_board_with_options(board.clone(), level, None).is_ok() == expected
+            }
+            None => true,
+        }
+    }
+}
+
+/// 条件を満たすシードを探す
+///
+/// `start..=end` のシードで盤面を生成し、条件を満たすものを昇順で最大 `limit` 個返します。
+/// 評価は全コアで並列に行いますが、結果はスレッド数に依存しません。
+/// 端数と整地の可否は `options.level` で判定します。
+///
+/// # 引数
+/// - `start`, `end`: 探索するシードの範囲（両端を含む）
+/// - `query`: 検索条件
+/// - `options`: 盤面生成オプション
+/// - `limit`: 返すシードの最大数
+///
+/// # 戻り値
+/// 条件を満たすシードのリストを返します。評価中にスレッドが異常終了した場合は`Err`を返します。
+pub fn search_seeds(
+    start: u32,
+    end: u32,
+    query: &SeedQuery,
+    options: &GenerateOptions,
+    limit: usize,
+) -> Result<Vec<u32>, String> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()) as u32;
+    let mut found: Vec<u32> = Vec::new();
+    if start > end || limit == 0 {
+        return Ok(found);
+    }
+
+    let mut batch_start = start;
+    loop {
+        let batch_end = batch_start.saturating_add(SEARCH_BATCH_SIZE - 1).min(end);
+
+        // バッチ内をスレッドごとに飛び飛びに分担する（seed ≡ t mod threads）
+        let mut batch: Vec<u32> = thread::scope(|scope| -> Result<Vec<u32>, String> {
+            let handles: Vec<_> = (0..threads)
+                .map(|t| {
+                    scope.spawn(move || {
+                        let mut hits = Vec::new();
+                        let mut seed = batch_start as u64 + t as u64;
+                        while seed <= batch_end as u64 {
+                            let board = generate_board_state_with_options(seed as u32, options);
+                            if query.matches(&board, &options.level) {
+                                hits.push(seed as u32);
+                            }
+                            seed += threads as u64;
+                        }
+                        hits
+                    })
+                })
+                .collect();
+            let mut hits = Vec::new();
+            for h in handles {
+                let worker = h
+                    .join()
+                    .map_err(|_| "seed search worker panicked".to_string())?;
+                hits.extend(worker);
+            }
+            Ok(hits)
+        })?;
+        batch.sort_unstable();
+        found.extend(batch);
+
+        if found.len() >= limit || batch_end == end {
+            break;
+        }
+        batch_start = batch_end + 1;
+    }
+
+    found.truncate(limit);
+    Ok(found)
+}
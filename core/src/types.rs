@@ -5,15 +5,83 @@ pub const BOARD_SIZE: usize = 19;
 /// 4方向の隣接セル（上下左右）
 pub const NEIGH4: [(isize, isize); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
 
+/// 盤面生成のシード
+///
+/// JSONでは数値または文字列です。
+/// - `u32` に収まる数値: 従来の32bit RNGを使う（以前と同じ盤面になる）
+/// - それより大きい数値: 既定の64bit RNGを使う
+/// - 文字列: FNV-1aで64bitに変換して既定の64bit RNGを使う
+///
+/// JavaScriptの数値で正確に表せない 2^53 以上の数値シードは10進の文字列で出力し、
+/// `u64` に収まる10進の数字だけの文字列（先頭に 0 を付けないもの）は数値シードとして読みます。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "SeedRepr", into = "SeedRepr")]
+pub enum Seed {
+    Number(u64),
+    Text(String),
+}
+
+/// JSONの数値で正確に表せる最大の整数（2^53 - 1）
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum SeedRepr {
+    Number(u64),
+    Text(String),
+}
+
+impl From<SeedRepr> for Seed {
+    fn from(repr: SeedRepr) -> Self {
+        match repr {
+            SeedRepr::Number(n) => Seed::Number(n),
+            SeedRepr::Text(s) => match parse_decimal_seed(&s) {
+                Some(n) => Seed::Number(n),
+                None => Seed::Text(s),
+            },
+        }
+    }
+}
+
+impl From<Seed> for SeedRepr {
+    fn from(seed: Seed) -> Self {
+        match seed {
+            Seed::Number(n) if n <= MAX_SAFE_INTEGER => SeedRepr::Number(n),
+            Seed::Number(n) => SeedRepr::Text(n.to_string()),
+            Seed::Text(s) => SeedRepr::Text(s),
+        }
+    }
+}
+
+/// 10進表記の数値シード（`"0"` または先頭が 0 でない数字列）を読む
+pub(crate) fn parse_decimal_seed(s: &str) -> Option<u64> {
+    let canonical = s == "0" || (!s.starts_with('0') && !s.is_empty());
+    if canonical && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+impl From<u32> for Seed {
+    fn from(seed: u32) -> Self {
+        Seed::Number(seed as u64)
+    }
+}
+
 /// 盤面状態:
 /// - stones: 0=空, 1=黒石, 2=白石
 /// - territory: 0=どちらでもない, 1=黒地, 2=白地
+/// - 地のセルに相手色の石がある場合、その石は死石
+/// - prisoners: アゲハマ [黒が取った白石の数, 白が取った黒石の数]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BoardState {
     pub size: u32,
-    pub seed: u32,
+    pub seed: Seed,
     pub stones: Vec<u8>,
     pub territory: Vec<u8>,
+    #[serde(default)]
+    pub prisoners: [u32; 2],
 }
 
 pub trait Logger {
@@ -25,6 +93,14 @@ pub fn idx2(size: usize, x: usize, y: usize) -> usize {
     y * size + x
 }
 
+/// セルインデックスを (x, y) 座標に変換する
+pub fn cell_coords(size: usize, cells: impl IntoIterator<Item = usize>) -> Vec<(u32, u32)> {
+    cells
+        .into_iter()
+        .map(|i| ((i % size) as u32, (i / size) as u32))
+        .collect()
+}
+
 /// 盤面上の指定色の石の位置を収集する
 pub fn collect_stone_positions(stones: &[u8], size: usize, color: u8) -> Vec<(usize, usize)> {
     let mut positions = Vec::new();
@@ -39,6 +115,45 @@ pub fn collect_stone_positions(stones: &[u8], size: usize, color: u8) -> Vec<(us
     positions
 }
 
+/// 死石（相手の地の上にある石）かどうか
+pub fn is_dead_stone(stones: &[u8], territory: &[u8], i: usize) -> bool {
+    stones[i] != 0 && territory[i] != 0 && stones[i] != territory[i]
+}
+
+/// 指定色の地の連結成分（4近傍）をセルインデックスのリストとして収集する
+pub fn collect_territory_regions(territory: &[u8], size: usize, color: u8) -> Vec<Vec<usize>> {
+    let mut visited = vec![false; size * size];
+    let mut regions = Vec::new();
+    for start in 0..(size * size) {
+        if territory[start] != color || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut queue = vec![start];
+        let mut region = vec![start];
+        while let Some(i) = queue.pop() {
+            let x = (i % size) as isize;
+            let y = (i / size) as isize;
+            for (dx, dy) in NEIGH4 {
+                let nx = x + dx;
+                let ny = y + dy;
+                if nx < 0 || ny < 0 || nx >= size as isize || ny >= size as isize {
+                    continue;
+                }
+                let ni = idx2(size, nx as usize, ny as usize);
+                if territory[ni] == color && !visited[ni] {
+                    visited[ni] = true;
+                    queue.push(ni);
+                    region.push(ni);
+                }
+            }
+        }
+        region.sort_unstable();
+        regions.push(region);
+    }
+    regions
+}
+
 /// 指定色の地の数をカウントする
 pub fn count_territory(territory: &[u8], color: u8) -> usize {
     territory.iter().filter(|&&t| t == color).count()
@@ -0,0 +1,219 @@
+use seiti_core::{
+    BOARD_SIZE, BoardState, GenerateOptions, Seed, SolverBackend, generate_board_state,
+    generate_board_state_from_seed, generate_board_state_with_options,
+    generate_levelable_board_state, level_board_with_options,
+};
+
+/// 石と地を FNV-1a 64bit でまとめたハッシュ
+fn board_hash(board: &BoardState) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in board.stones.iter().chain(&board.territory) {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
+
+/// `generate_board_state(u32)` が `Seed` 導入前と同じ盤面を生成すること
+///
+/// 期待値は `Seed` 導入前の実装で生成した盤面のハッシュです。
+#[test]
+fn u32_seeds_generate_the_same_boards_as_before() {
+    let golden: [(u32, u64); 6] = [
+        (0, 0x83ee_8fb7_ca61_8e31),
+        (1, 0x6c6c_6c0d_37d5_5959),
+        (2, 0xb841_2ea3_0597_b491),
+        (42, 0x32cd_70a5_c91b_d5e7),
+        (12345, 0x0fda_29c5_dd2a_fb05),
+        (4_000_000_000, 0x83cf_708d_8492_1c8c),
+    ];
+    for (seed, hash) in golden {
+        let board = generate_board_state(seed);
+        assert_eq!(board.size as usize, BOARD_SIZE);
+        assert_eq!(board.seed, Seed::Number(seed as u64));
+        assert_eq!(board_hash(&board), hash, "seed {seed}");
+        let from_seed = generate_board_state_from_seed(&seed.into(), &GenerateOptions::default());
+        assert_eq!(board_hash(&from_seed), hash, "seed {seed}");
+    }
+}
+
+#[test]
+fn large_numeric_seeds_are_serialized_as_strings() {
+    let safe = Seed::Number((1 << 53) - 1);
+    assert_eq!(serde_json::to_string(&safe).unwrap(), "9007199254740991");
+    let large = Seed::Number(u64::MAX);
+    let json = serde_json::to_string(&large).unwrap();
+    assert_eq!(json, "\"18446744073709551615\"");
+    assert_eq!(serde_json::from_str::<Seed>(&json).unwrap(), large);
+    assert_eq!(
+        serde_json::from_str::<Seed>("18446744073709551615").unwrap(),
+        large
+    );
+}
+
+#[test]
+fn decimal_strings_are_numeric_seeds() {
+    assert_eq!(
+        serde_json::from_str::<Seed>("\"12345\"").unwrap(),
+        Seed::Number(12345)
+    );
+    for text in ["daily-2026-10-17", "012", "", "18446744073709551616"] {
+        let json = serde_json::to_string(text).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Seed>(&json).unwrap(),
+            Seed::Text(text.to_string())
+        );
+    }
+    let options = GenerateOptions::default();
+    let text = generate_board_state_from_seed(&Seed::Text("12345".into()), &options);
+    assert_eq!(board_hash(&text), board_hash(&generate_board_state(12345)));
+}
+
+#[test]
+fn text_seeds_are_reproducible() {
+    let options = GenerateOptions::default();
+    let seed = Seed::Text("daily-2026-10-17".into());
+    let a = generate_board_state_from_seed(&seed, &options);
+    let b = generate_board_state_from_seed(&seed, &options);
+    assert_eq!(a.stones, b.stones);
+    assert_eq!(a.territory, b.territory);
+    assert_eq!(a.seed, seed);
+}
+
+#[test]
+fn ensure_levelable_verifies_with_the_given_level_options() {
+    let mut options = GenerateOptions {
+        ensure_levelable: true,
+        ..GenerateOptions::default()
+    };
+    options.level.unit = 5;
+    let seed = Seed::Number(1);
+    let (board, verified) = generate_levelable_board_state(&seed, &options);
+    assert!(verified);
+    assert!(level_board_with_options(board.clone(), &options.level, None).is_ok());
+
+    // フラグはほかの生成関数でも有効
+    let from_seed = generate_board_state_from_seed(&seed, &options);
+    assert_eq!(from_seed.stones, board.stones);
+    assert_eq!(from_seed.seed, board.seed);
+    let with_options = generate_board_state_with_options(1, &options);
+    assert_eq!(with_options.territory, board.territory);
+}
+
+/// 派生シードや地を埋めて直した盤面は、返したシードから同じ盤面を作れること
+#[test]
+fn levelable_seed_reproduces_the_board() {
+    // (シード, 動かさないセル, 返るシード): 動かさないセルに石がない盤面は整地できない
+    let cases = [(1, (3, 15), "1#1"), (30, (0, 0), "30+fill1")];
+    for (seed, cell, expected) in cases {
+        let mut options = GenerateOptions {
+            ensure_levelable: true,
+            ..GenerateOptions::default()
+        };
+        options.level.locked = vec![cell];
+        options.level.solver.backend = SolverBackend::Builtin;
+        let (board, verified) = generate_levelable_board_state(&Seed::Number(seed), &options);
+        assert!(verified, "seed {seed}");
+        assert_eq!(board.seed, Seed::Text(expected.into()), "seed {seed}");
+        assert!(level_board_with_options(board.clone(), &options.level, None).is_ok());
+        assert_ne!(board.stones, generate_board_state(seed as u32).stones);
+
+        let again = generate_board_state_from_seed(&board.seed, &GenerateOptions::default());
+        assert_eq!(again.stones, board.stones, "seed {expected}");
+        assert_eq!(again.territory, board.territory, "seed {expected}");
+        assert_eq!(again.seed, board.seed);
+    }
+}
+
+/// 上下左右の盤面内のセル
+fn neighbors(x: usize, y: usize) -> impl Iterator<Item = usize> {
+    [(1, 0), (-1, 0), (0, 1), (0, -1)]
+        .into_iter()
+        .map(move |(dx, dy)| (x as isize + dx, y as isize + dy))
+        .filter(|&(nx, ny)| {
+            nx >= 0 && ny >= 0 && nx < BOARD_SIZE as isize && ny < BOARD_SIZE as isize
+        })
+        .map(|(nx, ny)| ny as usize * BOARD_SIZE + nx as usize)
+}
+
+/// 中立点は指定した数まで黒白の境界に残り、地にも他の中立点にも接しないこと
+#[test]
+fn neutral_points_are_left_on_borders() {
+    let options = GenerateOptions {
+        neutral_points: 6,
+        ..GenerateOptions::default()
+    };
+    for seed in 1..=10 {
+        let board = generate_board_state_with_options(seed, &options);
+        let plain = generate_board_state(seed);
+        let is_neutral = |i: usize| board.stones[i] == 0 && board.territory[i] == 0;
+        let neutral: Vec<usize> = (0..BOARD_SIZE * BOARD_SIZE)
+            .filter(|&i| is_neutral(i))
+            .collect();
+        assert!(
+            (1..=options.neutral_points).contains(&neutral.len()),
+            "seed {seed}: {} neutral points",
+            neutral.len()
+        );
+        assert!(
+            (0..BOARD_SIZE * BOARD_SIZE).all(|i| plain.stones[i] != 0 || plain.territory[i] != 0)
+        );
+        for &i in &neutral {
+            let (x, y) = (i % BOARD_SIZE, i / BOARD_SIZE);
+            // 元の盤面では黒石と白石の境界にあった石
+            let color = plain.stones[i];
+            assert_ne!(color, 0, "seed {seed}: ({x}, {y})");
+            assert!(
+                neighbors(x, y).any(|j| plain.stones[j] == 3 - color),
+                "seed {seed}: ({x}, {y}) is not on a border"
+            );
+            for j in neighbors(x, y) {
+                assert_eq!(
+                    board.territory[j], 0,
+                    "seed {seed}: ({x}, {y}) touches territory"
+                );
+                assert!(
+                    !is_neutral(j),
+                    "seed {seed}: ({x}, {y}) touches a neutral point"
+                );
+            }
+        }
+    }
+}
+
+/// 死石は指定した数以下で、地の中の相手の生きた石に接しない位置にあり、アゲハマは上限以下であること
+#[test]
+fn dead_stones_and_prisoners_stay_within_their_bounds() {
+    let options = GenerateOptions {
+        dead_stones: 4,
+        max_prisoners: 3,
+        ..GenerateOptions::default()
+    };
+    let mut planted = 0;
+    for seed in 1..=10 {
+        let board = generate_board_state_with_options(seed, &options);
+        let dead: Vec<usize> = (0..BOARD_SIZE * BOARD_SIZE)
+            .filter(|&i| board.territory[i] != 0 && board.stones[i] != 0)
+            .collect();
+        let territory = board.territory.iter().filter(|&&t| t != 0).count();
+        assert!(dead.len() <= options.dead_stones, "seed {seed}: {dead:?}");
+        assert!(dead.len() <= territory / 10, "seed {seed}: {dead:?}");
+        for &i in &dead {
+            let (x, y) = (i % BOARD_SIZE, i / BOARD_SIZE);
+            let color = board.territory[i];
+            assert_eq!(board.stones[i], 3 - color, "seed {seed}: ({x}, {y})");
+            assert!(
+                neighbors(x, y).all(|j| board.stones[j] != 3 - color || board.territory[j] != 0),
+                "seed {seed}: ({x}, {y}) touches a live stone of its color"
+            );
+        }
+        planted += dead.len();
+        assert!(board.prisoners.iter().all(|&p| p <= options.max_prisoners));
+
+        // 死石を除けば、オプションなしの盤面と同じ
+        let plain = generate_board_state(seed);
+        assert_eq!(board.territory, plain.territory, "seed {seed}");
+        assert_eq!(plain.prisoners, [0, 0]);
+    }
+    assert!(planted > 0);
+}
@@ -0,0 +1,837 @@
+use seiti_core::{
+    AlignmentOptions, BOARD_SIZE, BoardState, CellRect, Diagnostic, LevelMode, LevelOptions,
+    Objective, PatternDef, PatternLibrary, PatternRole, Seed, SolveStatus, SolverBackend,
+    SolverOptions, compute_stone_moves, diagnose_leveling, generate_board_state, is_infeasible,
+    level_board_alternatives, level_board_with_options, solve_conflict_model,
+};
+use std::time::Instant;
+
+fn builtin_options() -> LevelOptions {
+    LevelOptions {
+        solver: SolverOptions {
+            backend: SolverBackend::Builtin,
+            ..SolverOptions::default()
+        },
+        ..LevelOptions::default()
+    }
+}
+
+/// 領域に分けて解いても、全体を1つのモデルで解いた場合と目的関数・盤面が同じになること
+///
+/// 3 以外のシードは、同じコストの解を決め直さなければ2つの解き方で盤面が変わる。
+#[test]
+fn decompose_matches_the_whole_model() {
+    let mut decomposed = 0;
+    for seed in [1, 3, 6, 9, 10, 14, 18, 19] {
+        let board = generate_board_state(seed);
+        let split = LevelOptions {
+            decompose: true,
+            ..builtin_options()
+        };
+        let whole = LevelOptions {
+            decompose: false,
+            ..builtin_options()
+        };
+        let a = level_board_alternatives(board.clone(), &split, 1, None).unwrap();
+        let b = level_board_alternatives(board, &whole, 1, None).unwrap();
+        assert!(
+            (a[0].solve.objective - b[0].solve.objective).abs() < 1e-6,
+            "seed {seed}: {} != {}",
+            a[0].solve.objective,
+            b[0].solve.objective
+        );
+        assert_eq!(a[0].board.stones, b[0].board.stones, "seed {seed}");
+        assert_eq!(a[0].board.territory, b[0].board.territory, "seed {seed}");
+        if !a[0].regions.is_empty() {
+            decomposed += 1;
+        }
+    }
+    assert!(decomposed > 0);
+}
+
+/// 衝突制約をクリークにしても、衝突する組ごとの制約と同じ最適値になること
+#[test]
+fn conflict_cliques_match_pairwise_conflicts() {
+    for seed in [1, 2, 3, 5] {
+        let board = generate_board_state(seed);
+        for color in [1u8, 2u8] {
+            let cliques =
+                solve_conflict_model(board.clone(), color, SolverBackend::Builtin, false).unwrap();
+            let pairs =
+                solve_conflict_model(board.clone(), color, SolverBackend::Builtin, true).unwrap();
+            assert!(cliques.constraints < pairs.constraints);
+            assert!(
+                (cliques.objective - pairs.objective).abs() < 1e-6,
+                "seed {seed} color {color}: {} != {}",
+                cliques.objective,
+                pairs.objective
+            );
+        }
+    }
+}
+
+/// 既定のライブラリから 4x5・5x6 を除いたもの（以前の既定のライブラリ）
+fn library_without_large_blocks() -> PatternLibrary {
+    let mut library = PatternLibrary::builtin();
+    library
+        .patterns
+        .retain(|p| p.name != "4x5" && p.name != "5x6");
+    library
+}
+
+/// 4x5・5x6 を加えても目的関数の値は以前の既定のライブラリと変わらず、大きな模様ではそれらを使うこと
+#[test]
+fn large_blocks_keep_the_objective() {
+    let mut large_blocks = 0;
+    for seed in [1, 3, 5, 6] {
+        let board = generate_board_state(seed);
+        let before = LevelOptions {
+            patterns: Some(library_without_large_blocks()),
+            ..builtin_options()
+        };
+        let a = level_board_with_options(board.clone(), &builtin_options(), None).unwrap();
+        let b = level_board_with_options(board, &before, None).unwrap();
+        assert!(
+            (a.solve.objective - b.solve.objective).abs() < 1e-6,
+            "seed {seed}: {} != {}",
+            a.solve.objective,
+            b.solve.objective
+        );
+
+        // 選んだ主パターンの点数の合計は、地を単位で切り捨てた数
+        for color in &a.report.colors {
+            let points: u32 = a
+                .report
+                .patterns
+                .iter()
+                .filter(|p| p.color == color.color && p.role == PatternRole::Main)
+                .map(|p| p.points)
+                .sum();
+            assert_eq!(points as usize, color.main_points, "seed {seed}");
+        }
+        large_blocks += a
+            .report
+            .patterns
+            .iter()
+            .filter(|p| p.name == "4x5" || p.name == "5x6")
+            .count();
+    }
+    assert!(large_blocks > 0);
+}
+
+/// 単位 `unit` で整地すると、主パターンの点数の合計は地を単位で切り捨てた数、端数はその余りになること
+fn check_unit_leveling(unit: usize, seeds: &[u32]) {
+    let options = LevelOptions {
+        unit,
+        solver: SolverOptions {
+            backend: SolverBackend::Builtin,
+            time_limit: Some(5.0),
+            ..SolverOptions::default()
+        },
+        ..LevelOptions::default()
+    };
+    for &seed in seeds {
+        let board = generate_board_state(seed);
+        let outcome = level_board_with_options(board.clone(), &options, None).unwrap();
+        for color in [1u8, 2u8] {
+            let territory = board.territory.iter().filter(|&&t| t == color).count();
+            let after = outcome
+                .board
+                .territory
+                .iter()
+                .filter(|&&t| t == color)
+                .count();
+            assert_eq!(after, territory, "unit {unit} seed {seed} color {color}");
+
+            let report = outcome
+                .report
+                .colors
+                .iter()
+                .find(|c| c.color == color)
+                .unwrap();
+            assert_eq!(report.main_points, territory / unit * unit);
+            assert_eq!(report.remainder as usize, territory % unit);
+            let points = |role: PatternRole| -> usize {
+                outcome
+                    .report
+                    .patterns
+                    .iter()
+                    .filter(|p| p.color == color && p.role == role)
+                    .map(|p| p.points as usize)
+                    .sum()
+            };
+            assert_eq!(points(PatternRole::Main), report.main_points);
+            assert_eq!(points(PatternRole::Remainder), territory % unit);
+        }
+    }
+}
+
+#[test]
+fn levels_in_units_of_five() {
+    check_unit_leveling(5, &[1]);
+}
+
+#[test]
+fn levels_in_units_of_twenty() {
+    check_unit_leveling(20, &[1, 2, 3]);
+}
+
+/// 動かさないセルの石は整地しても変わらず、禁止範囲にはパターンを置かず地も残らないこと
+#[test]
+fn locked_cells_and_forbidden_areas_are_respected() {
+    let size = BOARD_SIZE;
+    for seed in [1, 2, 3] {
+        let board = generate_board_state(seed);
+        // 上の4行の生きている石をすべて動かさない
+        let locked: Vec<(u32, u32)> = (0..4 * size)
+            .filter(|&i| board.stones[i] != 0 && board.territory[i] == 0)
+            .map(|i| ((i % size) as u32, (i / size) as u32))
+            .collect();
+        assert!(!locked.is_empty());
+        let forbidden = CellRect {
+            x: 12,
+            y: 12,
+            w: 5,
+            h: 5,
+        };
+        let inside = |i: usize| {
+            let (x, y) = ((i % size) as u32, (i / size) as u32);
+            (12..17).contains(&x) && (12..17).contains(&y)
+        };
+        assert!((0..size * size).any(|i| inside(i) && board.territory[i] != 0));
+
+        let options = LevelOptions {
+            locked: locked.clone(),
+            ..builtin_options()
+        };
+        let outcome = level_board_with_options(board.clone(), &options, None).unwrap();
+        for &(x, y) in &locked {
+            let i = y as usize * size + x as usize;
+            assert_eq!(outcome.board.stones[i], board.stones[i], "seed {seed}");
+        }
+
+        let options = LevelOptions {
+            forbidden: vec![forbidden],
+            ..builtin_options()
+        };
+        let outcome = level_board_with_options(board.clone(), &options, None).unwrap();
+        for i in (0..size * size).filter(|&i| inside(i)) {
+            assert_eq!(outcome.board.territory[i], 0, "seed {seed}");
+            assert_eq!(outcome.report.cells[i], None, "seed {seed}");
+        }
+    }
+}
+
+/// 動かさない石のために整地できない盤面では、整地がその石を動かすことを `ConstraintsInfeasible` で示すこと
+#[test]
+fn locked_stones_that_block_leveling_are_reported() {
+    let mut library = PatternLibrary::builtin();
+    library
+        .patterns
+        .retain(|p| p.name == "2x5" || p.slot == PatternRole::Remainder);
+    // 2x5 はどこに置いても (6, 3) の石を取り除く
+    let board = drawn_board(&[
+        ".........",
+        ".XXXXXXXX",
+        ".XxxxxxxX",
+        ".XxxxxXXX",
+        ".XXXXXXXX",
+    ]);
+    let free = LevelOptions {
+        patterns: Some(library),
+        ..builtin_options()
+    };
+    let outcome = level_board_with_options(board.clone(), &free, None).unwrap();
+    assert_eq!(outcome.board.stones[3 * BOARD_SIZE + 6], 0);
+
+    let locked = LevelOptions {
+        locked: vec![(6, 3)],
+        ..free.clone()
+    };
+    let err = level_board_with_options(board.clone(), &locked, None).unwrap_err();
+    assert!(is_infeasible(&err), "{err}");
+    let diags = diagnose_leveling(board, &locked).unwrap();
+    assert_eq!(
+        diags.last(),
+        Some(&Diagnostic::ConstraintsInfeasible {
+            cells: vec![(6, 3)],
+        }),
+        "{diags:?}"
+    );
+}
+
+/// 同色の石を同色の地として数えた盤面は、ふつうに整地すると石の数が変わり、
+/// 石の数を保つ設定では整地せずに `StonesNotConserved` を返すこと
+#[test]
+fn conserving_stones_rejects_boards_whose_stone_count_changes() {
+    for seed in [1, 2, 3] {
+        let mut board = generate_board_state(seed);
+        // 黒の地の石のないセルに黒石を置く（地のまま数える）
+        let i = (0..board.stones.len())
+            .find(|&i| board.territory[i] == 1 && board.stones[i] == 0)
+            .unwrap();
+        board.stones[i] = 1;
+        let count = |stones: &[u8], color: u8| stones.iter().filter(|&&s| s == color).count();
+
+        let outcome = level_board_with_options(board.clone(), &builtin_options(), None).unwrap();
+        assert_ne!(
+            count(&outcome.board.stones, 1),
+            count(&board.stones, 1),
+            "seed {seed}"
+        );
+
+        let options = LevelOptions {
+            conserve_stones: true,
+            ..builtin_options()
+        };
+        let err = level_board_with_options(board.clone(), &options, None).unwrap_err();
+        assert!(err.contains("cannot be conserved"), "seed {seed}: {err}");
+        let diags = diagnose_leveling(board, &options).unwrap();
+        assert!(
+            diags.iter().any(|d| matches!(
+                d,
+                Diagnostic::StonesNotConserved { color: 1, points, empty_territory, .. }
+                    if *points == *empty_territory + 1
+            )),
+            "seed {seed}: {diags:?}"
+        );
+    }
+}
+
+/// コストが同じ解が複数ある盤面でも、選ぶパターンはソルバーと領域分割の有無によらず決まること
+#[test]
+fn deterministic_leveling_is_stable() {
+    // シード 3 は、決め直さなければ組み込みソルバーと microlp で選ぶパターンが変わる
+    let expected = "1:2x4@0,10 1:2x5@17,2 1:2x5@6,14 1:4x5@0,13 \
+                    2:2x5@1,0 2:2x5@17,11 2:2x5@8,0 2:3x4@2,3";
+    let board = generate_board_state(3);
+    let mut backends = vec![SolverBackend::Builtin, SolverBackend::ExactCover];
+    if SolverBackend::Microlp.is_available() {
+        backends.push(SolverBackend::Microlp);
+    }
+    for backend in backends {
+        for decompose in [true, false] {
+            let options = LevelOptions {
+                decompose,
+                solver: SolverOptions {
+                    backend,
+                    ..SolverOptions::default()
+                },
+                ..LevelOptions::default()
+            };
+            let outcome = level_board_with_options(board.clone(), &options, None).unwrap();
+            let mut patterns: Vec<String> = outcome
+                .report
+                .patterns
+                .iter()
+                .map(|p| format!("{}:{}@{},{}", p.color, p.name, p.x, p.y))
+                .collect();
+            patterns.sort();
+            assert_eq!(
+                patterns.join(" "),
+                expected,
+                "{backend:?} decompose={decompose}"
+            );
+        }
+    }
+}
+
+#[test]
+fn invalid_constraints_are_rejected() {
+    let board = generate_board_state(1);
+    let size = BOARD_SIZE;
+    let empty = (0..size * size)
+        .find(|&i| board.stones[i] == 0)
+        .map(|i| ((i % size) as u32, (i / size) as u32))
+        .unwrap();
+    let rect = |x, y, w, h| CellRect { x, y, w, h };
+    let invalid = [
+        // 石のないセル・盤外のセルは動かさないセルにできない
+        (vec![empty], vec![]),
+        (vec![(19, 0)], vec![]),
+        // 空の長方形・盤面からはみ出す長方形
+        (vec![], vec![rect(0, 0, 0, 3)]),
+        (vec![], vec![rect(15, 15, 5, 2)]),
+    ];
+    for (locked, forbidden) in invalid {
+        let options = LevelOptions {
+            locked: locked.clone(),
+            forbidden: forbidden.clone(),
+            ..builtin_options()
+        };
+        assert!(
+            level_board_with_options(board.clone(), &options, None).is_err(),
+            "{locked:?} {forbidden:?}"
+        );
+    }
+}
+
+/// 途中で止められない microlp に制限時間を指定するとエラーになること
+#[test]
+fn microlp_rejects_time_limit() {
+    let options = LevelOptions {
+        solver: SolverOptions {
+            backend: SolverBackend::Microlp,
+            time_limit: Some(1.0),
+            ..SolverOptions::default()
+        },
+        ..LevelOptions::default()
+    };
+    let err = level_board_with_options(generate_board_state(1), &options, None).unwrap_err();
+    assert!(err.contains("time_limit"), "{err}");
+}
+
+/// 探索ソルバーは制限時間を大きく超えずに打ち切ること
+#[test]
+fn search_solvers_honor_the_time_limit() {
+    for backend in [SolverBackend::Builtin, SolverBackend::ExactCover] {
+        let options = LevelOptions {
+            unit: 5,
+            solver: SolverOptions {
+                backend,
+                time_limit: Some(1.0),
+                ..SolverOptions::default()
+            },
+            ..LevelOptions::default()
+        };
+        let started = Instant::now();
+        let outcome = level_board_with_options(generate_board_state(7), &options, None).unwrap();
+        let elapsed = started.elapsed().as_secs_f64();
+        assert_eq!(outcome.solve.status, SolveStatus::TimeLimit, "{backend:?}");
+        assert!(elapsed < 3.0, "{backend:?} took {elapsed:.2}s");
+    }
+}
+
+/// 左上から文字で描いた盤面（'X'・'O' は黒・白の石、'x'・'o' は黒・白の地、ほかはどちらでもないセル）
+fn drawn_board(rows: &[&str]) -> BoardState {
+    let size = BOARD_SIZE;
+    let mut stones = vec![0u8; size * size];
+    let mut territory = vec![0u8; size * size];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, ch) in row.chars().enumerate() {
+            let i = y * size + x;
+            match ch {
+                'X' => stones[i] = 1,
+                'O' => stones[i] = 2,
+                'x' => territory[i] = 1,
+                'o' => territory[i] = 2,
+                _ => {}
+            }
+        }
+    }
+    BoardState {
+        size: size as u32,
+        seed: Seed::Number(0),
+        stones,
+        territory,
+        prisoners: [0, 0],
+    }
+}
+
+/// 整地できない理由ごとに、その理由だけを返す盤面があること
+/// （`StonesNotConserved` は `conserving_stones_rejects_boards_whose_stone_count_changes` で確かめる）
+#[test]
+fn each_diagnostic_has_a_board() {
+    // 主パターンを 2x5 だけにして、置ける位置を数えやすくする
+    let mut library = PatternLibrary::builtin();
+    library
+        .patterns
+        .retain(|p| p.name == "2x5" || p.slot == PatternRole::Remainder);
+    let options = LevelOptions {
+        patterns: Some(library),
+        ..builtin_options()
+    };
+    let diagnose = |rows: &[&str], options: &LevelOptions| {
+        let board = drawn_board(rows);
+        assert!(level_board_with_options(board.clone(), options, None).is_err());
+        diagnose_leveling(board, options).unwrap()
+    };
+
+    // 幅1の地には 2x5 が入らない
+    let line = [
+        ".............",
+        ".XXXXXXXXXXXX",
+        ".XxxxxxxxxxxX",
+        ".XXXXXXXXXXXX",
+    ];
+    assert_eq!(
+        diagnose(&line, &options),
+        vec![Diagnostic::NoMainCandidates {
+            color: 1,
+            main_points: 10,
+        }]
+    );
+
+    // 1セルずつの地では、3セルの端数パターンを置けない
+    let dots = [
+        "............",
+        ".XXX.XXX.XXX",
+        ".XxX.XxX.XxX",
+        ".XXX.XXX.XXX",
+    ];
+    assert_eq!(
+        diagnose(&dots, &options),
+        vec![Diagnostic::NoRemainderCandidates {
+            color: 1,
+            remainder: 3,
+        }]
+    );
+
+    // 上の連結成分には 2x5 が入るが、下の幅1の連結成分には入らない
+    let region = [
+        ".............",
+        ".XXXXXXX.....",
+        ".XxxxxxX.....",
+        ".XxxxxxX.....",
+        ".XXXXXXX.....",
+        ".............",
+        ".XXXXXXXXXXXX",
+        ".XxxxxxxxxxxX",
+        ".XXXXXXXXXXXX",
+    ];
+    assert_eq!(
+        diagnose(&region, &options),
+        vec![Diagnostic::RegionLacksMain {
+            color: 1,
+            cells: (2..12).map(|x| (x, 7)).collect(),
+            needed: 10,
+            max_points: 0,
+            candidates: 0,
+        }]
+    );
+
+    // どちらの連結成分にも間の石の行にかかる 2x5 を置けるが、2枚は必ず接する（覆えるのは地の5セル）
+    let shared = [
+        ".............",
+        ".XXXXXXXXXXXX",
+        ".XxxxxxxxxxxX",
+        ".XXXXXXXXXXXX",
+        ".XxxxxxxxxxxX",
+        ".XXXXXXXXXXXX",
+    ];
+    match &diagnose(&shared, &options)[..] {
+        [
+            Diagnostic::MainConflicts {
+                color: 1,
+                main_points: 20,
+                max_points: 10,
+                cells,
+            },
+        ] => assert_eq!(cells.len(), 15),
+        other => panic!("{other:?}"),
+    }
+
+    // 端数の1セルは、地をちょうど覆う 2x5 に必ず接する
+    let notch = [
+        ".........",
+        ".XXXXXXX.",
+        ".XxxxxxX.",
+        ".XxxxxxX.",
+        ".XXxXXXX.",
+        "..XXX....",
+    ];
+    match &diagnose(&notch, &options)[..] {
+        [
+            Diagnostic::RemainderConflicts {
+                color: 1,
+                remainder: 1,
+                cells,
+            },
+        ] => assert!(cells.contains(&(3, 4))),
+        other => panic!("{other:?}"),
+    }
+
+    // 黒と白の地が接していて、両色の 2x5 が接してしまう
+    let joint = LevelOptions {
+        mode: LevelMode::Joint,
+        ..options.clone()
+    };
+    let touching = [
+        ".............",
+        ".XXXXXXOOOOOO",
+        ".XxxxxxoooooO",
+        ".XxxxxxoooooO",
+        ".XXXXXXOOOOOO",
+    ];
+    assert_eq!(
+        diagnose(&touching, &joint),
+        vec![Diagnostic::ColorConflicts {
+            cells: vec![(6, 2), (7, 2), (6, 3), (7, 3)],
+        }]
+    );
+
+    // 禁止範囲がなければ地をちょうど覆う 2x5 を置ける
+    let forbidden = LevelOptions {
+        forbidden: vec![CellRect {
+            x: 2,
+            y: 2,
+            w: 1,
+            h: 1,
+        }],
+        ..options.clone()
+    };
+    let block = ["........", ".XXXXXXX", ".XxxxxxX", ".XxxxxxX", ".XXXXXXX"];
+    assert_eq!(
+        diagnose(&block, &forbidden),
+        vec![
+            Diagnostic::NoMainCandidates {
+                color: 1,
+                main_points: 10,
+            },
+            Diagnostic::ConstraintsInfeasible {
+                cells: vec![(2, 2)],
+            },
+        ]
+    );
+}
+
+/// 石を動かした距離（マンハッタン距離）の合計
+fn moved_distance(before: &BoardState, after: &BoardState) -> u32 {
+    compute_stone_moves(before, after)
+        .unwrap()
+        .iter()
+        .map(|m| m.from.0.abs_diff(m.to.0) + m.from.1.abs_diff(m.to.1))
+        .sum()
+}
+
+/// `EdgeDistance` は目安なので盤面ごとには `Penalty` より長く動かすこともあるが、
+/// 複数の盤面の合計では `Penalty` より遠くへ動かさないこと
+#[test]
+fn edge_distance_does_not_move_stones_farther_than_penalty() {
+    let (mut penalty, mut edge) = (0, 0);
+    for seed in 1..=5 {
+        let board = generate_board_state(seed);
+        for (objective, total) in [
+            (Objective::Penalty, &mut penalty),
+            (Objective::EdgeDistance, &mut edge),
+        ] {
+            let options = LevelOptions {
+                objective,
+                ..builtin_options()
+            };
+            let outcome = level_board_with_options(board.clone(), &options, None).unwrap();
+            *total += moved_distance(&board, &outcome.board);
+        }
+    }
+    assert!(edge <= penalty, "{edge} > {penalty}");
+
+    // 以前の名前でも指定できる
+    let objective: Objective = serde_json::from_str("\"distance\"").unwrap();
+    assert_eq!(objective, Objective::EdgeDistance);
+}
+
+/// 両色を1つのモデルで選ぶと、目的関数は順次整地以下で、両色のパターンが重ならず接しないこと
+#[test]
+fn joint_leveling_beats_sequential_and_keeps_colors_apart() {
+    let joint = LevelOptions {
+        mode: LevelMode::Joint,
+        ..builtin_options()
+    };
+    // 両色を1つのモデルにすると builtin では盤面によって数秒かかるので、速く解けるシードで確かめる
+    for seed in [3, 5] {
+        let board = generate_board_state(seed);
+        let sequential = level_board_with_options(board.clone(), &builtin_options(), None).unwrap();
+        let outcome = level_board_with_options(board, &joint, None).unwrap();
+        assert!(
+            outcome.solve.objective <= sequential.solve.objective + 1e-6,
+            "seed {seed}: joint {} > sequential {}",
+            outcome.solve.objective,
+            sequential.solve.objective
+        );
+
+        let size = outcome.board.size as i64;
+        let report = &outcome.report;
+        let color_at = |x: i64, y: i64| {
+            if x < 0 || y < 0 || x >= size || y >= size {
+                return None;
+            }
+            report.cells[(y * size + x) as usize].map(|id| report.patterns[id].color)
+        };
+        let covered: usize = report.patterns.iter().map(|p| p.cells.len()).sum();
+        assert_eq!(
+            covered,
+            report.cells.iter().flatten().count(),
+            "seed {seed}: patterns overlap"
+        );
+        for p in &report.patterns {
+            for &(x, y) in &p.cells {
+                for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                    let other = color_at(x as i64 + dx, y as i64 + dy);
+                    assert!(
+                        other.is_none_or(|c| c == p.color),
+                        "seed {seed}: colors touch at ({x}, {y})"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// k 通りの整地結果は互いに異なる盤面で、目的関数の小さい順に並ぶこと
+#[test]
+fn alternatives_are_distinct_and_sorted() {
+    for mode in [LevelMode::Sequential, LevelMode::Joint] {
+        let options = LevelOptions {
+            mode,
+            ..builtin_options()
+        };
+        let outcomes =
+            level_board_alternatives(generate_board_state(3), &options, 3, None).unwrap();
+        assert_eq!(outcomes.len(), 3, "{mode:?}");
+        for (i, a) in outcomes.iter().enumerate() {
+            for b in &outcomes[i + 1..] {
+                assert!(
+                    a.board.stones != b.board.stones || a.board.territory != b.board.territory,
+                    "{mode:?}: the same board twice"
+                );
+            }
+        }
+        for pair in outcomes.windows(2) {
+            assert!(
+                pair[0].solve.objective <= pair[1].solve.objective,
+                "{mode:?}: {} > {}",
+                pair[0].solve.objective,
+                pair[1].solve.objective
+            );
+        }
+    }
+}
+
+/// 端数は `remainder_pieces` 以下の数の端数パターンに分けられ、大きさの合計は端数に一致すること
+#[test]
+fn remainder_pieces_is_an_upper_bound() {
+    // (シード, remainder_pieces が 3 のときの黒の端数パターンの数)
+    for (seed, black_pieces) in [(2, 3), (3, 2)] {
+        let board = generate_board_state(seed);
+        let mut previous = f64::INFINITY;
+        for pieces in 1..=3 {
+            let options = LevelOptions {
+                remainder_pieces: pieces,
+                extra_piece_penalty: 0.0,
+                ..builtin_options()
+            };
+            let outcome = level_board_with_options(board.clone(), &options, None).unwrap();
+            let report = &outcome.report;
+            for color in report.colors.iter() {
+                let sizes: Vec<u32> = report
+                    .patterns
+                    .iter()
+                    .filter(|p| p.color == color.color && p.role == PatternRole::Remainder)
+                    .map(|p| p.points)
+                    .collect();
+                assert!(sizes.len() <= pieces, "seed {seed}: {sizes:?} > {pieces}");
+                assert_eq!(sizes.iter().sum::<u32>(), color.remainder as u32);
+                if pieces == 3 && color.color == 1 {
+                    assert_eq!(sizes.len(), black_pieces, "seed {seed}: {sizes:?}");
+                }
+            }
+            // 分けてよい数を増やしても目的関数は大きくならない
+            assert!(outcome.solve.objective <= previous, "seed {seed}");
+            previous = outcome.solve.objective;
+        }
+    }
+}
+
+/// L字のパターンは形どおりのセルに置かれ、外周も形に沿って調べること
+#[test]
+fn l_shaped_patterns_follow_their_cells() {
+    let mut library = PatternLibrary::builtin();
+    library.patterns = vec![PatternDef {
+        name: "L".to_string(),
+        slot: PatternRole::Main,
+        points: None,
+        cells: vec!["######".to_string(), "####..".to_string()],
+        rotate: false,
+        mirror: false,
+        pair_long_edge: false,
+    }];
+    let options = LevelOptions {
+        patterns: Some(library),
+        ..builtin_options()
+    };
+    let l_shape = [
+        ".........",
+        ".XXXXXXXX",
+        ".XxxxxxxX",
+        ".XxxxxXXX",
+        ".XXXXXX..",
+    ];
+    let outcome = level_board_with_options(drawn_board(&l_shape), &options, None).unwrap();
+    let mut expected: Vec<(u32, u32)> = (2..=7).map(|x| (x, 2)).collect();
+    expected.extend((2..=5).map(|x| (x, 3)));
+    match &outcome.report.patterns[..] {
+        [picked] => {
+            assert_eq!(picked.name, "L");
+            let mut cells = picked.cells.clone();
+            cells.sort_by_key(|&(x, y)| (y, x));
+            assert_eq!(cells, expected);
+        }
+        other => panic!("{other:?}"),
+    }
+    let size = outcome.board.size;
+    let territory: Vec<(u32, u32)> = (0..size * size)
+        .filter(|&i| outcome.board.territory[i as usize] == 1)
+        .map(|i| (i % size, i / size))
+        .collect();
+    assert_eq!(territory, expected);
+
+    // 欠けた角の中（外接する長方形の内側）にどちらでもないセルがあれば、そこは外周なので置けない
+    let mut notch = l_shape;
+    notch[3] = ".Xxxxx.XX";
+    assert!(level_board_with_options(drawn_board(&notch), &options, None).is_err());
+    assert!(matches!(
+        &diagnose_leveling(drawn_board(&notch), &options).unwrap()[..],
+        [Diagnostic::NoMainCandidates { color: 1, .. }]
+    ));
+}
+
+/// 並べ方の好みを指定すると、主パターンを盤端に接する位置や盤端からそろった位置に置くこと
+#[test]
+fn alignment_moves_main_patterns() {
+    let count_mains = |seed: u32, alignment: AlignmentOptions| {
+        let options = LevelOptions {
+            alignment,
+            ..builtin_options()
+        };
+        let outcome = level_board_with_options(generate_board_state(seed), &options, None).unwrap();
+        let size = outcome.board.size;
+        let mains: Vec<_> = outcome
+            .report
+            .patterns
+            .into_iter()
+            .filter(|p| p.role == PatternRole::Main)
+            .collect();
+        let on_edge = mains
+            .iter()
+            .filter(|p| p.x == 0 || p.y == 0 || p.x + p.w == size || p.y + p.h == size)
+            .count();
+        let aligned = mains
+            .iter()
+            .filter(|p| {
+                let dx = p.x.min(size - p.x - p.w);
+                let dy = p.y.min(size - p.y - p.h);
+                dx % p.w == 0 && dy % p.h == 0
+            })
+            .count();
+        (mains.len(), on_edge, aligned)
+    };
+    for seed in [3, 5] {
+        let (_, on_edge, aligned) = count_mains(seed, AlignmentOptions::default());
+
+        let edge = AlignmentOptions {
+            prefer_edge: 20,
+            ..AlignmentOptions::default()
+        };
+        let (mains, all_on_edge, _) = count_mains(seed, edge);
+        assert_eq!(all_on_edge, mains, "seed {seed}");
+        assert!(on_edge < all_on_edge, "seed {seed}");
+
+        let rows = AlignmentOptions {
+            prefer_aligned_rows: 20,
+            ..AlignmentOptions::default()
+        };
+        let (mains, _, all_aligned) = count_mains(seed, rows);
+        assert_eq!(all_aligned, mains, "seed {seed}");
+        assert!(aligned < all_aligned, "seed {seed}");
+    }
+}
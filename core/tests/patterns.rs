@@ -0,0 +1,112 @@
+use seiti_core::{PatternDef, PatternLibrary, PatternRole};
+
+fn def(name: &str, slot: PatternRole, cells: &[&str]) -> PatternDef {
+    PatternDef {
+        name: name.to_string(),
+        slot,
+        points: None,
+        cells: cells.iter().map(|r| r.to_string()).collect(),
+        rotate: false,
+        mirror: false,
+        pair_long_edge: false,
+    }
+}
+
+fn library(patterns: Vec<PatternDef>) -> PatternLibrary {
+    PatternLibrary { unit: 10, patterns }
+}
+
+/// 既定のライブラリを TOML・JSON に書き出して読み直すと同じになること
+#[test]
+fn builtin_library_round_trips() {
+    let builtin = PatternLibrary::builtin();
+    builtin.validate().unwrap();
+
+    let toml = toml::to_string(&builtin).unwrap();
+    assert_eq!(PatternLibrary::from_toml(&toml).unwrap(), builtin);
+    let json = serde_json::to_string(&builtin).unwrap();
+    assert_eq!(PatternLibrary::from_json(&json).unwrap(), builtin);
+}
+
+#[test]
+fn invalid_libraries_are_rejected() {
+    let main = def("2x5", PatternRole::Main, &["#####", "#####"]);
+
+    // 主パターンがない
+    let only_rem = library(vec![def("1x3", PatternRole::Remainder, &["###"])]);
+    assert!(only_rem.validate().is_err());
+    // 主パターンの点数が単位の倍数でない
+    let odd_main = library(vec![def("3x3", PatternRole::Main, &["###", "###", "###"])]);
+    assert!(odd_main.validate().is_err());
+    // 端数パターンが単位以上
+    let big_rem = library(vec![
+        main.clone(),
+        def("2x5", PatternRole::Remainder, &["#####", "#####"]),
+    ]);
+    assert!(big_rem.validate().is_err());
+    // 点数がアンカー以外のセル数と違う
+    let wrong_points = library(vec![PatternDef {
+        points: Some(20),
+        ..main.clone()
+    }]);
+    assert!(wrong_points.validate().is_err());
+    // 行の長さがそろっていない・つながっていない・知らない文字
+    for cells in [
+        &["#####", "####"][..],
+        &["#####", "....."],
+        &["##x##", "#####"],
+    ] {
+        let bad = library(vec![
+            main.clone(),
+            def("bad", PatternRole::Remainder, cells),
+        ]);
+        assert!(bad.validate().is_err(), "{cells:?}");
+    }
+    // pair_long_edge は長方形だけ
+    let bad_pair = library(vec![PatternDef {
+        pair_long_edge: true,
+        ..def("L", PatternRole::Main, &["######", "####.."])
+    }]);
+    assert!(bad_pair.validate().is_err());
+    // 単位が範囲外
+    let bad_unit = PatternLibrary {
+        unit: 1,
+        patterns: vec![main.clone()],
+    };
+    assert!(bad_unit.validate().is_err());
+
+    assert!(library(vec![main]).validate().is_ok());
+    assert!(PatternLibrary::from_toml("unit = 10\npatterns = 3").is_err());
+    assert!(PatternLibrary::from_json("{\"patterns\": [{}]}").is_err());
+}
+
+/// 単位から自動生成したライブラリは、点数がアンカー以外のセル数と一致し、
+/// 主パターンは単位の倍数、端数パターンは 1..単位 の各大きさをそろえていること
+#[test]
+fn generated_libraries_count_non_anchor_cells() {
+    for unit in 2..=30 {
+        let library = PatternLibrary::for_unit(unit).unwrap();
+        assert_eq!(library.unit, unit);
+        library.validate().unwrap();
+
+        let mut remainders = vec![false; unit];
+        for def in &library.patterns {
+            let cells = def.cells.iter().flat_map(|r| r.chars());
+            let counted = cells.filter(|&c| c == '#').count();
+            if let Some(points) = def.points {
+                assert_eq!(points as usize, counted, "unit {unit}: {}", def.name);
+            }
+            match def.slot {
+                PatternRole::Main => {
+                    assert!(
+                        counted > 0 && counted.is_multiple_of(unit),
+                        "unit {unit}: {}",
+                        def.name
+                    );
+                }
+                PatternRole::Remainder => remainders[counted] = true,
+            }
+        }
+        assert!(remainders[1..].iter().all(|&r| r), "unit {unit}");
+    }
+}
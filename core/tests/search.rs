@@ -0,0 +1,36 @@
+use seiti_core::{
+    ColorQuery, GenerateOptions, SeedQuery, generate_board_state_with_options, search_seeds,
+};
+
+fn black_territory(seed: u32, options: &GenerateOptions) -> usize {
+    let board = generate_board_state_with_options(seed, options);
+    board.territory.iter().filter(|&&t| t == 1).count()
+}
+
+#[test]
+fn remainder_is_counted_in_the_level_unit() {
+    let mut options = GenerateOptions::default();
+    options.level.unit = 5;
+    let query = SeedQuery {
+        black: ColorQuery {
+            remainder: Some(3),
+            ..ColorQuery::default()
+        },
+        ..SeedQuery::default()
+    };
+    let seeds = search_seeds(0, 99, &query, &options, 100).unwrap();
+    let expected: Vec<u32> = (0..=99)
+        .filter(|&s| black_territory(s, &options) % 5 == 3)
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(seeds, expected);
+}
+
+#[test]
+fn limit_keeps_the_smallest_seeds() {
+    let options = GenerateOptions::default();
+    let all = search_seeds(0, 199, &SeedQuery::default(), &options, 1000).unwrap();
+    assert_eq!(all, (0..=199).collect::<Vec<u32>>());
+    let first = search_seeds(0, 199, &SeedQuery::default(), &options, 7).unwrap();
+    assert_eq!(first, (0..7).collect::<Vec<u32>>());
+}
@@ -6,9 +6,10 @@ type Territory = 0 | 1 | 2 // 0=なし, 1=黒地, 2=白地
 
 type BoardState = {
   size: number
-  seed: number
+  seed: number | string
   stones: ArrayLike<number>
   territory: ArrayLike<number>
+  prisoners?: [number, number] // アゲハマ [黒が取った白石, 白が取った黒石]
 }
 
 type StoneMove = {
@@ -17,9 +18,64 @@ type StoneMove = {
   to: [number, number] // [x, y]
 }
 
-type LevelResp = {
+type SolveStatus = 'optimal' | 'gap_limit' | 'time_limit'
+
+type RegionOutcome = {
+  color: number // 1=黒, 2=白
+  cells: [number, number][] // [x, y]
+  main_points: number
+  remainder: boolean
+  status: SolveStatus
+  objective: number
+  gap?: number
+}
+
+type PickedPattern = {
+  id: number
+  color: number // 1=黒, 2=白
+  name: string
+  role: 'main' | 'remainder'
+  points: number // 10目ずつ数えるなら 10・20・30 など
+  x: number
+  y: number
+  w: number
+  h: number
+  cells: [number, number][] // [x, y]
+  anchors: [number, number][] // [x, y]
+  cost: number
+  penalty_perimeter: number
+  penalty_internal: number
+  penalty_alignment: number
+}
+
+type LevelReport = {
+  colors: {
+    color: number
+    main_points: number
+    remainder: number
+    main_candidates: number
+    remainder_candidates: number
+  }[]
+  solve: { status: SolveStatus; objective: number; gap?: number }
+  patterns: PickedPattern[]
+  cells: (number | null)[] // y * 19 + x の順。パターンのないセルは null
+}
+
+type BlockEdge = { x1: number; y1: number; x2: number; y2: number; color: number }
+type BlockLabel = { id: number; x: number; y: number; points: number; color: number }
+
+type LevelAlternative = {
   board: BoardState
   moves: StoneMove[]
+  status: SolveStatus
+  objective: number
+  gap?: number
+  regions?: RegionOutcome[] // 領域に分けて解いた場合のみ
+  report: LevelReport
+}
+
+type LevelResp = LevelAlternative & {
+  alternatives?: LevelAlternative[] // k >= 2 の場合のみ
 }
 
 // アニメーションタイミング定数
@@ -97,6 +153,7 @@ function GoBoard19(props: {
   moves?: StoneMove[]
   animating?: boolean
   animatingPending?: boolean
+  report?: LevelReport | null
 }) {
   const lines = Array.from({ length: 19 }, (_, i) => i) // 0..18
   const star = [3, 9, 15]
@@ -111,6 +168,33 @@ function GoBoard19(props: {
   const animating = props.animating ?? false
   const animatingPending = props.animatingPending ?? false
 
+  // パターンの境界線（隣のセルと番号が違う辺）と、点数のラベルの位置（セルの重心）
+  const patternCells = props.report?.cells
+  const patternList = props.report?.patterns
+  const blocks = useMemo<{ edges: BlockEdge[]; labels: BlockLabel[] }>(() => {
+    if (!patternCells || !patternList || size !== 19) return { edges: [], labels: [] }
+    const idAt = (x: number, y: number) =>
+      x < 0 || y < 0 || x >= 19 || y >= 19 ? null : patternCells[y * 19 + x]
+    const edges: BlockEdge[] = []
+    for (const p of patternList) {
+      for (const [x, y] of p.cells) {
+        if (idAt(x, y - 1) !== p.id) edges.push({ x1: x - 0.5, y1: y - 0.5, x2: x + 0.5, y2: y - 0.5, color: p.color })
+        if (idAt(x, y + 1) !== p.id) edges.push({ x1: x - 0.5, y1: y + 0.5, x2: x + 0.5, y2: y + 0.5, color: p.color })
+        if (idAt(x - 1, y) !== p.id) edges.push({ x1: x - 0.5, y1: y - 0.5, x2: x - 0.5, y2: y + 0.5, color: p.color })
+        if (idAt(x + 1, y) !== p.id) edges.push({ x1: x + 0.5, y1: y - 0.5, x2: x + 0.5, y2: y + 0.5, color: p.color })
+      }
+    }
+    const labels = patternList.map((p) => ({
+      id: p.id,
+      x: p.cells.reduce((s, [x]) => s + x, 0) / p.cells.length,
+      y: p.cells.reduce((s, [, y]) => s + y, 0) / p.cells.length,
+      points: p.points,
+      color: p.color,
+    }))
+    return { edges, labels }
+  }, [patternCells, patternList, size])
+  const showBlocks = !animating && !animatingPending
+
   const territoryDots = useMemo(() => {
     if (!territory || size !== 19) return []
     const out: Array<{ x: number; y: number; v: Territory }> = []
@@ -212,6 +296,32 @@ function GoBoard19(props: {
 
         {/* stones */}
         <StoneCircles stoneDots={stoneDots} animating={animating} />
+
+        {/* leveled blocks (outline and points) */}
+        {showBlocks && (
+          <g>
+            {blocks.edges.map((e, i) => (
+              <line
+                key={`b:${i}`}
+                x1={e.x1}
+                y1={e.y1}
+                x2={e.x2}
+                y2={e.y2}
+                className={e.color === 1 ? 'goBlockEdgeBlack' : 'goBlockEdgeWhite'}
+              />
+            ))}
+            {blocks.labels.map((l) => (
+              <text
+                key={`l:${l.id}`}
+                x={l.x}
+                y={l.y}
+                className={l.color === 1 ? 'goBlockLabelBlack' : 'goBlockLabelWhite'}
+              >
+                {l.points}
+              </text>
+            ))}
+          </g>
+        )}
       </svg>
     </div>
   )
@@ -223,6 +333,7 @@ function App() {
   const [board, setBoard] = useState<BoardState | null>(null)
   const [leveledBoard, setLeveledBoard] = useState<BoardState | null>(null)
   const [moves, setMoves] = useState<StoneMove[]>([])
+  const [report, setReport] = useState<LevelReport | null>(null)
   const [animating, setAnimating] = useState(false)
   const [animationPending, setAnimationPending] = useState(false)
 
@@ -248,7 +359,8 @@ function App() {
       if (v === 1) black++
       else if (v === 2) white++
     }
-    return { black, white }
+    const [blackPrisoners, whitePrisoners] = board.prisoners ?? [0, 0]
+    return { black, white, blackPrisoners, whitePrisoners }
   }, [board])
 
   const generateBoard = useCallback(() => {
@@ -262,6 +374,7 @@ function App() {
         setBoard(v)
         setLeveledBoard(null)
         setMoves([])
+        setReport(null)
         setAnimating(false)
         setAnimationPending(false)
         setStatus('idle')
@@ -283,6 +396,7 @@ function App() {
         const resp = await postJson<LevelResp>('/api/board/level', { board })
         setLeveledBoard(resp.board)
         setMoves(resp.moves)
+        setReport(resp.report)
         setStatus('idle')
       } catch (e) {
         console.error('level failed', e)
@@ -317,6 +431,7 @@ function App() {
         setBoard(v)
         setLeveledBoard(null)
         setMoves([])
+        setReport(null)
         setAnimating(false)
         setAnimationPending(false)
         setStatus('idle')
@@ -351,6 +466,8 @@ function App() {
         {territoryCounts && (
           <span className="goCounts">
             黒地: {territoryCounts.black} / 白地: {territoryCounts.white}
+            {(territoryCounts.blackPrisoners > 0 || territoryCounts.whitePrisoners > 0) &&
+              ` (アゲハマ 黒: ${territoryCounts.blackPrisoners} / 白: ${territoryCounts.whitePrisoners})`}
           </span>
         )}
       </div>
@@ -368,6 +485,7 @@ function App() {
               moves={moves} 
               animating={animating}
               animatingPending={animationPending}
+              report={report}
             />
           ) : (
             <p className="goHint">未整地です（整地ボタンを押してください）</p>